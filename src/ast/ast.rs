@@ -1,75 +1,413 @@
-use crate::token;
+use crate::objects::bigint::BigInt;
+use crate::token::{self, Span};
 use std::fmt;
+use std::rc::Rc;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Expression {
-    Ident(String),
-    NoExprsn,
-    Int(i32),
+    Ident(String, Span),
+    Int(i64, Span),
+    /// An integer literal too large for an `i64`.
+    BigInt(BigInt, Span),
+    Float(f64, Span),
+    Str(String, Span),
+    /// A string with embedded `${...}` expressions; literal text appears
+    /// as `Str` parts.
+    Interpolated {
+        parts: Vec<Expression>,
+        span: Span
+    },
     Boolean {
         token: token::Token,
-        value:bool
+        value:bool,
+        span: Span
     },
     PrefixExprsn {
         token: token::Token,
-        exprsn: Box<Expression>
+        exprsn: Box<Expression>,
+        span: Span
     },
     InfixExprsn {
         left: Box<Expression>,
         right: Box<Expression>,
-        oprt: String
+        oprt: String,
+        span: Span
     },
     IfExprsn{
         condt: Box<Expression>,
         conseq: Vec<Statement>,
-        alter: Vec<Statement>
+        alter: Vec<Statement>,
+        span: Span
     },
     FnExprsn{
         params: Vec<Expression>,
-        body: Vec<Statement>
+        /// Shared with every function value made from this literal.
+        body: Rc<Vec<Statement>>,
+        span: Span
+    },
+    Call{
+        function: Box<Expression>,
+        args: Vec<Expression>,
+        span: Span
+    },
+    Array{
+        elements: Vec<Expression>,
+        span: Span
+    },
+    Index{
+        left: Box<Expression>,
+        index: Box<Expression>,
+        span: Span
+    },
+    Hash{
+        pairs: Vec<(Expression, Expression)>,
+        span: Span
     }
 }
 
+impl Expression {
+    /// The source span this expression was parsed from.
+    pub fn span(&self) -> Span {
+        match self{
+            Expression::Ident(_, span)
+            | Expression::Int(_, span)
+            | Expression::BigInt(_, span)
+            | Expression::Float(_, span)
+            | Expression::Str(_, span)
+            | Expression::Interpolated { span, .. }
+            | Expression::Boolean { span, .. }
+            | Expression::PrefixExprsn { span, .. }
+            | Expression::InfixExprsn { span, .. }
+            | Expression::IfExprsn { span, .. }
+            | Expression::FnExprsn { span, .. }
+            | Expression::Call { span, .. }
+            | Expression::Array { span, .. }
+            | Expression::Index { span, .. }
+            | Expression::Hash { span, .. } => *span,
+        }
+    }
+}
+
+/// Prints the expression back as source, with every prefix, infix and
+/// index expression parenthesised so the grouping the parser chose shows.
 impl fmt::Display for Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self{
-            Expression::Ident(s)=> write!(f, "{}", s),
-            Expression::IfExprsn{ condt, conseq, alter }=> write!(f, "{condt}\n, {:?}\n {:?}\n", conseq, alter),
-            Expression::FnExprsn{ params, body }=> write!(f, "FUNCTION params: {:?} Body: {:?}", params, body),
-            Expression::Boolean{token, value} =>  write!(f, " here is the statement: {} {}", token, value),
-            Expression::PrefixExprsn{token, exprsn} =>  write!(f, "PrefixExprsn: {token} {exprsn}"),
-            Expression::InfixExprsn{left, oprt,right} =>  write!(f, "InfixExprsn: {left} {oprt} {right} "),
-            Expression::Int(s)=> write!(f, "{}", s),
-            Expression::NoExprsn => write!(f, "we are skippin' the expression for now")
+            Expression::Ident(s, _)=> write!(f, "{}", s),
+            Expression::Int(s, _)=> write!(f, "{}", s),
+            Expression::BigInt(s, _)=> write!(f, "{}", s),
+            Expression::Float(s, _)=> write!(f, "{:?}", s),
+            Expression::Str(s, _)=> write!(f, "{:?}", s),
+            Expression::Interpolated{parts, ..} => {
+                write!(f, "\"")?;
+                for part in parts{
+                    match part{
+                        Expression::Str(s, _) => write!(f, "{}", s.escape_debug())?,
+                        other => write!(f, "${{{other}}}")?
+                    }
+                }
+                write!(f, "\"")
+            },
+            Expression::Boolean{value, ..} => write!(f, "{value}"),
+            Expression::PrefixExprsn{token, exprsn, ..} => write!(f, "({token}{exprsn})"),
+            Expression::InfixExprsn{left, oprt, right, ..} => write!(f, "({left} {oprt} {right})"),
+            Expression::IfExprsn{ condt, conseq, alter, .. } => {
+                write!(f, "if {condt} ")?;
+                fmt_block(conseq, f)?;
+                if !alter.is_empty(){
+                    write!(f, " else ")?;
+                    fmt_block(alter, f)?;
+                }
+                Ok(())
+            },
+            Expression::FnExprsn{ params, body, .. } => {
+                write!(f, "fn(")?;
+                fmt_list(params, f)?;
+                write!(f, ") ")?;
+                fmt_block(body, f)
+            },
+            Expression::Call{function, args, ..} => {
+                write!(f, "{function}(")?;
+                fmt_list(args, f)?;
+                write!(f, ")")
+            },
+            Expression::Array{elements, ..} => {
+                write!(f, "[")?;
+                fmt_list(elements, f)?;
+                write!(f, "]")
+            },
+            Expression::Index{left, index, ..} => write!(f, "({left}[{index}])"),
+            Expression::Hash{pairs, ..} => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in pairs.iter().enumerate(){
+                    if i > 0{
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{key}: {value}")?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+fn fmt_list(items:&[Expression], f:&mut fmt::Formatter<'_>) -> fmt::Result {
+    for (i, item) in items.iter().enumerate(){
+        if i > 0{
+            write!(f, ", ")?;
         }
+        write!(f, "{item}")?;
     }
+    Ok(())
 }
 
+fn fmt_block(stmnts:&[Statement], f:&mut fmt::Formatter<'_>) -> fmt::Result {
+    if stmnts.is_empty(){
+        return write!(f, "{{}}")
+    }
+    write!(f, "{{")?;
+    for s in stmnts{
+        write!(f, " {s}")?;
+    }
+    write!(f, " }}")
+}
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[allow(clippy::enum_variant_names)]
 pub enum Statement {
     Let {
         token:token::Token, 
         ident:Expression,
-        exprs:Expression
+        exprs:Expression,
+        span: Span
     },
 
     Return {
         token:token::Token,
-        exprs:Expression
+        exprs:Expression,
+        span: Span
     },
     ExprsStatement{
         token:token::Token,
-        exprs:Expression
+        exprs:Expression,
+        span: Span
+    }
+}
+
+/// The statements of a whole source file, in order.
+pub type Program = Vec<Statement>;
+
+// lets tests compare the shape of a tree against one built by hand
+#[cfg(test)]
+impl Statement {
+    pub(crate) fn without_spans(&self) -> Statement {
+        let span = Span::default();
+        match self{
+            Statement::Let{token, ident, exprs, ..} => Statement::Let {
+                token: token.clone(), ident: ident.without_spans(), exprs: exprs.without_spans(), span
+            },
+            Statement::Return{token, exprs, ..} => Statement::Return { token: token.clone(), exprs: exprs.without_spans(), span },
+            Statement::ExprsStatement{token, exprs, ..} => Statement::ExprsStatement {
+                token: token.clone(), exprs: exprs.without_spans(), span
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+impl Expression {
+    pub(crate) fn without_spans(&self) -> Expression {
+        let span = Span::default();
+        let list = |items:&[Expression]| items.iter().map(Expression::without_spans).collect();
+        let block = |stmnts:&[Statement]| stmnts.iter().map(Statement::without_spans).collect();
+        let boxed = |e:&Expression| Box::new(e.without_spans());
+        match self{
+            Expression::Ident(v, _) => Expression::Ident(v.clone(), span),
+            Expression::Int(v, _) => Expression::Int(*v, span),
+            Expression::BigInt(v, _) => Expression::BigInt(v.clone(), span),
+            Expression::Float(v, _) => Expression::Float(*v, span),
+            Expression::Str(v, _) => Expression::Str(v.clone(), span),
+            Expression::Interpolated{parts, ..} => Expression::Interpolated { parts: list(parts), span },
+            Expression::Boolean{token, value, ..} => Expression::Boolean { token: token.clone(), value: *value, span },
+            Expression::PrefixExprsn{token, exprsn, ..} => Expression::PrefixExprsn { token: token.clone(), exprsn: boxed(exprsn), span },
+            Expression::InfixExprsn{left, right, oprt, ..} => Expression::InfixExprsn {
+                left: boxed(left), right: boxed(right), oprt: oprt.clone(), span
+            },
+            Expression::IfExprsn{condt, conseq, alter, ..} => Expression::IfExprsn {
+                condt: boxed(condt), conseq: block(conseq), alter: block(alter), span
+            },
+            Expression::FnExprsn{params, body, ..} => Expression::FnExprsn { params: list(params), body: Rc::new(block(body)), span },
+            Expression::Call{function, args, ..} => Expression::Call { function: boxed(function), args: list(args), span },
+            Expression::Array{elements, ..} => Expression::Array { elements: list(elements), span },
+            Expression::Index{left, index, ..} => Expression::Index { left: boxed(left), index: boxed(index), span },
+            Expression::Hash{pairs, ..} => Expression::Hash {
+                pairs: pairs.iter().map(|(k, v)| (k.without_spans(), v.without_spans())).collect(),
+                span
+            },
+        }
     }
 }
 
 impl fmt::Display for Statement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self{
-            Statement::Let{token, ident, exprs} =>  write!(f, " here is the statement: {} {} {}", token, ident, exprs),
-            Statement::ExprsStatement{token, exprs} =>  write!(f, "{} {}", token, exprs),
-            Statement::Return{token, exprs} =>  write!(f, " here is the statement: {} {}", token, exprs),
+            Statement::Let{ident, exprs, ..} => write!(f, "let {ident} = {exprs};"),
+            Statement::Return{exprs, ..} => write!(f, "return {exprs};"),
+            Statement::ExprsStatement{exprs, ..} => write!(f, "{exprs}"),
+        }
+    }
+}
+
+/// Renders a program as an indented tree, one node per line with the
+/// position it starts at.
+pub fn tree(program:&[Statement]) -> String {
+    let mut out = String::new();
+    for s in program{
+        tree_stmnt(s, 0, &mut out);
+    }
+    out
+}
+
+fn tree_line(out:&mut String, depth:usize, label:&str, span:Span) {
+    out.push_str(&format!("{}{label} @{}:{}\n", "  ".repeat(depth), span.line, span.col));
+}
+
+fn tree_stmnt(stmnt:&Statement, depth:usize, out:&mut String) {
+    match stmnt{
+        Statement::Let{ident, exprs, span, ..} => {
+            tree_line(out, depth, &format!("Let {ident}"), *span);
+            tree_exprs(exprs, depth + 1, out);
+        },
+        Statement::Return{exprs, span, ..} => {
+            tree_line(out, depth, "Return", *span);
+            tree_exprs(exprs, depth + 1, out);
+        },
+        Statement::ExprsStatement{exprs, ..} => tree_exprs(exprs, depth, out),
+    }
+}
+
+fn tree_block(label:&str, stmnts:&[Statement], span:Span, depth:usize, out:&mut String) {
+    tree_line(out, depth, label, span);
+    for s in stmnts{
+        tree_stmnt(s, depth + 1, out);
+    }
+}
+
+fn tree_exprs(exprs:&Expression, depth:usize, out:&mut String) {
+    let span = exprs.span();
+    match exprs{
+        Expression::Ident(s, _) => tree_line(out, depth, &format!("Ident {s}"), span),
+        Expression::Int(v, _) => tree_line(out, depth, &format!("Int {v}"), span),
+        Expression::BigInt(v, _) => tree_line(out, depth, &format!("Int {v}"), span),
+        Expression::Float(v, _) => tree_line(out, depth, &format!("Float {v:?}"), span),
+        Expression::Str(v, _) => tree_line(out, depth, &format!("Str {v:?}"), span),
+        Expression::Boolean{value, ..} => tree_line(out, depth, &format!("Bool {value}"), span),
+        Expression::Interpolated{parts, ..} => {
+            tree_line(out, depth, "Interpolated", span);
+            for part in parts{
+                tree_exprs(part, depth + 1, out);
+            }
+        },
+        Expression::PrefixExprsn{token, exprsn, ..} => {
+            tree_line(out, depth, &format!("Prefix {token}"), span);
+            tree_exprs(exprsn, depth + 1, out);
+        },
+        Expression::InfixExprsn{left, right, oprt, ..} => {
+            tree_line(out, depth, &format!("Infix {oprt}"), span);
+            tree_exprs(left, depth + 1, out);
+            tree_exprs(right, depth + 1, out);
+        },
+        Expression::IfExprsn{condt, conseq, alter, ..} => {
+            tree_line(out, depth, "If", span);
+            tree_exprs(condt, depth + 1, out);
+            tree_block("Then", conseq, span, depth + 1, out);
+            if !alter.is_empty(){
+                tree_block("Else", alter, span, depth + 1, out);
+            }
+        },
+        Expression::FnExprsn{params, body, ..} => {
+            let params: Vec<String> = params.iter().map(|p| p.to_string()).collect();
+            tree_block(&format!("Fn({})", params.join(", ")), body, span, depth, out);
+        },
+        Expression::Call{function, args, ..} => {
+            tree_line(out, depth, "Call", span);
+            tree_exprs(function, depth + 1, out);
+            for arg in args{
+                tree_exprs(arg, depth + 1, out);
+            }
+        },
+        Expression::Array{elements, ..} => {
+            tree_line(out, depth, "Array", span);
+            for e in elements{
+                tree_exprs(e, depth + 1, out);
+            }
+        },
+        Expression::Index{left, index, ..} => {
+            tree_line(out, depth, "Index", span);
+            tree_exprs(left, depth + 1, out);
+            tree_exprs(index, depth + 1, out);
+        },
+        Expression::Hash{pairs, ..} => {
+            tree_line(out, depth, "Hash", span);
+            for (key, value) in pairs{
+                tree_line(out, depth + 1, "Pair", key.span().to(value.span()));
+                tree_exprs(key, depth + 2, out);
+                tree_exprs(value, depth + 2, out);
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::ast::tree;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn parse(src:&str) -> Vec<crate::ast::ast::Statement> {
+        let mut p = Parser::new(Box::new(Lexer::new(src.to_string())));
+        p.parse_program().unwrap()
+    }
+
+    #[test]
+    fn test_display(){
+        let cases = [
+            ("let x = 1 + 2 * 3;", "let x = (1 + (2 * 3));"),
+            ("return -a[0];", "return (-(a[0]));"),
+            ("!true == false", "((!true) == false)"),
+            ("if x < 1 { 1.5 } else { let y = \"s\"; y }", "if (x < 1) { 1.5 } else { let y = \"s\"; y }"),
+            ("if x {}", "if x {}"),
+            ("fn(a, b) { a }(1, [2, 3])", "fn(a, b) { a }(1, [2, 3])"),
+            ("{\"k\": \"v${n + 1}!\"}", "{\"k\": \"v${(n + 1)}!\"}"),
+        ];
+        for (src, expected) in cases{
+            let program = parse(src);
+            assert_eq!(program[0].to_string(), expected, "{}", src);
+            // the printed form parses back to itself
+            assert_eq!(parse(expected)[0].to_string(), expected, "{}", src);
         }
     }
+
+    #[test]
+    fn test_tree(){
+        let src = "let f = fn(x) {\n  if x { [x, {1: x}] }\n};\nf(-1);";
+        let expected = "\
+Let f @1:1
+  Fn(x) @1:9
+    If @2:3
+      Ident x @2:6
+      Then @2:3
+        Array @2:10
+          Ident x @2:11
+          Hash @2:14
+            Pair @2:15
+              Int 1 @2:15
+              Ident x @2:18
+Call @4:1
+  Ident f @4:1
+  Prefix - @4:3
+    Int 1 @4:4
+";
+        assert_eq!(tree(&parse(src)), expected);
+    }
 }
@@ -0,0 +1,110 @@
+use crate::eval::RuntimeError;
+use crate::parser::parser::ParseError;
+use crate::token::Span;
+use std::fmt::Write;
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// An error tied to a place in the source, ready to be shown to the user.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>, span: Span) -> Self {
+        Diagnostic { message: message.into(), span }
+    }
+
+    /// Renders the diagnostic rustc-style: the message, the location, the
+    /// offending source line and a row of carets under the span. With
+    /// `colour` set the output is decorated with ANSI escapes.
+    pub fn render(&self, file_name: &str, source: &str, colour: bool) -> String {
+        let paint = |style: &'static str| if colour { style } else { "" };
+        let (red, blue, bold, reset) = (paint(RED), paint(BLUE), paint(BOLD), paint(RESET));
+
+        let bytes = source.as_bytes();
+        let start = self.span.start.min(bytes.len());
+        let line_start = bytes[..start].iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+        let line_end = bytes[start..].iter().position(|b| *b == b'\n').map_or(bytes.len(), |i| start + i);
+        let end = self.span.end.clamp(start, line_end);
+        let text = String::from_utf8_lossy(&bytes[line_start..line_end]);
+        let text = text.trim_end_matches('\r');
+
+        // keep tabs so the carets line up with the source line
+        let padding: String = bytes[line_start..start].iter()
+            .filter(|b| is_char_start(**b))
+            .map(|b| if *b == b'\t' { '\t' } else { ' ' })
+            .collect();
+        let width = bytes[start..end].iter().filter(|b| is_char_start(**b)).count().max(1);
+
+        let line_no = self.span.line.to_string();
+        let gutter = " ".repeat(line_no.len());
+        let mut out = String::new();
+        let _ = writeln!(out, "{red}error{reset}{bold}: {}{reset}", self.message);
+        let _ = writeln!(out, "{gutter}{blue}-->{reset} {file_name}:{}:{}", self.span.line, self.span.col);
+        let _ = writeln!(out, "{gutter} {blue}|{reset}");
+        let _ = writeln!(out, "{blue}{line_no} |{reset} {text}");
+        let _ = writeln!(out, "{gutter} {blue}|{reset} {padding}{red}{}{reset}", "^".repeat(width));
+        out
+    }
+}
+
+fn is_char_start(b: u8) -> bool {
+    b & 0xC0 != 0x80
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(e: &ParseError) -> Self {
+        Diagnostic::error(e.to_string(), e.span)
+    }
+}
+
+impl From<&RuntimeError> for Diagnostic {
+    fn from(e: &RuntimeError) -> Self {
+        Diagnostic::error(e.to_string(), e.span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::Diagnostic;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use crate::token::Span;
+
+    #[test]
+    fn test_render_parse_error(){
+        let src = "let x = 1;\n\tlet = 2;\n";
+        let mut p = Parser::new(Box::new(Lexer::new(src.to_string())));
+        let errors = p.parse_program().unwrap_err();
+        let out = Diagnostic::from(&errors[0]).render("main.mk", src, false);
+        let expected = "error: expected an identifier, found `=`
+ --> main.mk:2:6
+  |
+2 | \tlet = 2;
+  | \t    ^
+";
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_render_wide_span_and_colour(){
+        let src = "1 + 2;";
+        let out = Diagnostic::error("oops", Span::new(0, 5, 1, 1)).render("x", src, false);
+        assert!(out.ends_with("  | ^^^^^\n"), "{}", out);
+        let coloured = Diagnostic::error("oops", Span::new(0, 5, 1, 1)).render("x", src, true);
+        assert!(coloured.starts_with("\x1b[1;31merror\x1b[0m"));
+    }
+
+    #[test]
+    fn test_render_at_end_of_input(){
+        let src = "if x { 1;";
+        let out = Diagnostic::error("expected `}`", Span::new(9, 9, 1, 10)).render("x", src, false);
+        assert!(out.ends_with("1 | if x { 1;\n  |          ^\n"), "{}", out);
+    }
+}
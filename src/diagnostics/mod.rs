@@ -0,0 +1,3 @@
+pub mod diagnostics;
+
+pub use diagnostics::Diagnostic;
@@ -0,0 +1,186 @@
+use crate::eval::error::{ErrorKind, RuntimeError};
+use crate::eval::eval::Output;
+use crate::objects::objects::{NativeFn, NativeResult, Object};
+use std::io::Write;
+use std::rc::Rc;
+
+type Builtin = fn(&[Object]) -> NativeResult;
+
+/// The functions every program can call without defining them. They sit
+/// behind the environment, so a `let` of the same name shadows them.
+const BUILTINS: &[(&str, Builtin)] = &[
+    ("len", len),
+    ("first", first),
+    ("last", last),
+    ("rest", rest),
+    ("push", push),
+    ("type", type_of),
+    ("str", str),
+];
+
+pub fn lookup(name: &str, out: &Output) -> Option<Object> {
+    // `puts` is bound to the output of the evaluation that looks it up
+    if name == "puts" {
+        let out = Rc::clone(out);
+        return Some(Object::Builtin(NativeFn::new(name, move |args| puts(args, &mut *out.borrow_mut()))));
+    }
+    BUILTINS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(name, func)| Object::Builtin(NativeFn::new(name, *func)))
+}
+
+fn check_arg_count(args: &[Object], expected: usize) -> Result<(), RuntimeError> {
+    if args.len() != expected {
+        return Err(RuntimeError::at_call(ErrorKind::WrongArgCount { expected, found: args.len() }));
+    }
+    Ok(())
+}
+
+fn invalid_argument(function: &str, expected: &'static str, found: &Object) -> RuntimeError {
+    RuntimeError::at_call(ErrorKind::InvalidArgument { function: function.to_string(), expected, found: found.type_name() })
+}
+
+fn array_arg<'a>(function: &str, arg: &'a Object) -> Result<&'a [Object], RuntimeError> {
+    match arg {
+        Object::Array(items) => Ok(items),
+        other => Err(invalid_argument(function, "array", other)),
+    }
+}
+
+/// Characters in a string, elements in an array or entries in a hash.
+fn len(args: &[Object]) -> NativeResult {
+    check_arg_count(args, 1)?;
+    let n = match &args[0] {
+        Object::Str(s) => s.chars().count(),
+        Object::Array(items) => items.len(),
+        Object::Hash(pairs) => pairs.len(),
+        other => return Err(invalid_argument("len", "string, array or hash", other)),
+    };
+    Ok(Object::Int(n as i64))
+}
+
+/// Prints each argument on its own line.
+fn puts(args: &[Object], out: &mut dyn Write) -> NativeResult {
+    for arg in args {
+        writeln!(out, "{arg}").map_err(|e| RuntimeError::at_call(ErrorKind::Output(e.to_string())))?;
+    }
+    Ok(Object::Null)
+}
+
+fn first(args: &[Object]) -> NativeResult {
+    check_arg_count(args, 1)?;
+    let items = array_arg("first", &args[0])?;
+    Ok(items.first().cloned().unwrap_or(Object::Null))
+}
+
+fn last(args: &[Object]) -> NativeResult {
+    check_arg_count(args, 1)?;
+    let items = array_arg("last", &args[0])?;
+    Ok(items.last().cloned().unwrap_or(Object::Null))
+}
+
+/// Everything but the first element, or `null` for an empty array.
+fn rest(args: &[Object]) -> NativeResult {
+    check_arg_count(args, 1)?;
+    match array_arg("rest", &args[0])? {
+        [] => Ok(Object::Null),
+        [_, tail @ ..] => Ok(Object::Array(tail.to_vec())),
+    }
+}
+
+/// A copy of the array with one element added; the original is unchanged.
+fn push(args: &[Object]) -> NativeResult {
+    check_arg_count(args, 2)?;
+    let mut items = array_arg("push", &args[0])?.to_vec();
+    items.push(args[1].clone());
+    Ok(Object::Array(items))
+}
+
+fn type_of(args: &[Object]) -> NativeResult {
+    check_arg_count(args, 1)?;
+    Ok(Object::Str(args[0].type_name().to_string()))
+}
+
+fn str(args: &[Object]) -> NativeResult {
+    check_arg_count(args, 1)?;
+    Ok(Object::Str(args[0].to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::eval::{eval_program, Context};
+    use crate::eval::error::ErrorKind;
+    use crate::eval::{Environment, RuntimeError};
+    use crate::lexer::Lexer;
+    use crate::objects::objects::Object;
+    use crate::parser::Parser;
+
+    fn eval(src: &str) -> Result<Object, RuntimeError> {
+        let mut p = Parser::new(Box::new(Lexer::new(src.to_string())));
+        let program = p.parse_program().unwrap();
+        eval_program(&program, &Environment::new(), &mut Context::default())
+    }
+
+    #[test]
+    fn test_builtins() {
+        let cases = [
+            ("len(\"\")", "0"),
+            ("len(\"héllo\")", "5"),
+            ("len([1, 2, 3])", "3"),
+            ("len({\"a\": 1})", "1"),
+            ("first([1, 2, 3])", "1"),
+            ("first([])", "null"),
+            ("last([1, 2, 3])", "3"),
+            ("last([])", "null"),
+            ("rest([1, 2, 3])", "[2, 3]"),
+            ("rest([1])", "[]"),
+            ("rest([])", "null"),
+            ("let a = [1]; let b = push(a, \"x\"); [a, b]", "[[1], [1, \"x\"]]"),
+            ("type(1)", "int"),
+            ("type(99999999999999999999)", "int"),
+            ("type(\"a\")", "string"),
+            ("type(len)", "function"),
+            ("type(fn() { 1 })", "function"),
+            ("str(1.5) + str([1, \"a\"])", "1.5[1, \"a\"]"),
+            ("puts(\"hi\", 1)", "null"),
+            ("len", "builtin len"),
+            ("let len = fn(x) { 42 }; len([])", "42"),
+        ];
+        for (src, expected) in cases {
+            assert_eq!(eval(src).unwrap().to_string(), expected, "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_higher_order_builtins() {
+        let src = "
+            let map = fn(arr, f) {
+                let iter = fn(arr, acc) {
+                    if len(arr) == 0 { return acc; }
+                    iter(rest(arr), push(acc, f(first(arr))))
+                };
+                iter(arr, [])
+            };
+            map([1, 2, 3], fn(x) { x * 2 })";
+        assert_eq!(eval(src).unwrap().to_string(), "[2, 4, 6]");
+        assert_eq!(eval("let apply = fn(f, x) { f(x) }; apply(len, \"abc\")").unwrap().to_string(), "3");
+    }
+
+    #[test]
+    fn test_builtin_errors() {
+        let errors = [
+            ("len(1)", ErrorKind::InvalidArgument { function: "len".to_string(), expected: "string, array or hash", found: "int" }),
+            ("first(\"ab\")", ErrorKind::InvalidArgument { function: "first".to_string(), expected: "array", found: "string" }),
+            ("push(1, 2)", ErrorKind::InvalidArgument { function: "push".to_string(), expected: "array", found: "int" }),
+            ("len([], [])", ErrorKind::WrongArgCount { expected: 1, found: 2 }),
+            ("push([])", ErrorKind::WrongArgCount { expected: 2, found: 1 }),
+        ];
+        for (src, expected) in errors {
+            assert_eq!(eval(src).unwrap_err().kind, expected, "{}", src);
+        }
+        let err = eval("let x = 1;\nlen(x)").unwrap_err();
+        assert_eq!((err.span.line, err.span.col), (2, 1));
+        assert_eq!(err.to_string(), "`len` expects string, array or hash, got int");
+    }
+}
@@ -0,0 +1,53 @@
+use crate::objects::objects::Object;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// A shared handle to a scope. Closures keep the scope they were defined in
+/// alive through it.
+pub type Env = Rc<RefCell<Environment>>;
+
+/// One lexical scope: its own bindings plus the scope it is nested in.
+#[derive(Default)]
+pub struct Environment {
+    store: HashMap<String, Object>,
+    outer: Option<Env>,
+}
+
+impl Environment {
+    pub fn new() -> Env {
+        Rc::new(RefCell::new(Environment::default()))
+    }
+
+    /// A fresh scope whose lookups fall back to `outer`.
+    pub fn new_enclosed(outer: Env) -> Env {
+        Rc::new(RefCell::new(Environment { store: HashMap::new(), outer: Some(outer) }))
+    }
+
+    /// Looks `name` up in this scope and then in each enclosing one.
+    pub fn get(&self, name: &str) -> Option<Object> {
+        match self.store.get(name) {
+            Some(v) => Some(v.clone()),
+            None => self.outer.as_ref().and_then(|o| o.borrow().get(name)),
+        }
+    }
+
+    /// Binds `name` in this scope, shadowing any outer binding.
+    pub fn set(&mut self, name: String, val: Object) {
+        self.store.insert(name, val);
+    }
+}
+
+// Functions hold on to their defining scope, which may in turn hold the
+// function, so only the local names are printed.
+impl fmt::Debug for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<&String> = self.store.keys().collect();
+        names.sort();
+        f.debug_struct("Environment")
+            .field("names", &names)
+            .field("has_outer", &self.outer.is_some())
+            .finish()
+    }
+}
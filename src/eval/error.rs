@@ -0,0 +1,63 @@
+use crate::token::Span;
+use std::fmt;
+
+/// What went wrong while evaluating.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ErrorKind {
+    UnknownIdentifier(String),
+    NotCallable(&'static str),
+    WrongArgCount { expected: usize, found: usize },
+    UnsupportedOperand { oprt: String, operand: &'static str },
+    TypeMismatch { left: &'static str, oprt: String, right: &'static str },
+    UnknownOperator { left: &'static str, oprt: String, right: &'static str },
+    DivisionByZero,
+    NotIndexable(&'static str),
+    InvalidIndex { container: &'static str, index: &'static str },
+    IndexOutOfBounds { index: String, len: usize },
+    UnhashableKey(&'static str),
+    InvalidArgument { function: String, expected: &'static str, found: &'static str },
+    StackOverflow { limit: usize },
+    /// Writing to the program's output failed, e.g. on a closed pipe.
+    Output(String),
+}
+
+/// An error raised by the evaluator. It stops evaluation and carries the
+/// span of the expression that caused it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RuntimeError {
+    pub kind: ErrorKind,
+    pub span: Span,
+}
+
+impl RuntimeError {
+    pub fn new(kind: ErrorKind, span: Span) -> Self {
+        RuntimeError { kind, span }
+    }
+
+    /// An error raised by a native function. It has no span of its own:
+    /// the evaluator points it at the call that failed.
+    pub fn at_call(kind: ErrorKind) -> Self {
+        RuntimeError::new(kind, Span::default())
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ErrorKind::UnknownIdentifier(name) => write!(f, "unknown identifier `{name}`"),
+            ErrorKind::NotCallable(ty) => write!(f, "a value of type {ty} is not callable"),
+            ErrorKind::WrongArgCount { expected, found } => write!(f, "expected {expected} argument(s), got {found}"),
+            ErrorKind::UnsupportedOperand { oprt, operand } => write!(f, "unsupported operand type for `{oprt}`: {operand}"),
+            ErrorKind::TypeMismatch { left, oprt, right } => write!(f, "type mismatch: {left} {oprt} {right}"),
+            ErrorKind::UnknownOperator { left, oprt, right } => write!(f, "unknown operator: {left} {oprt} {right}"),
+            ErrorKind::DivisionByZero => write!(f, "division by zero"),
+            ErrorKind::NotIndexable(ty) => write!(f, "a value of type {ty} can't be indexed"),
+            ErrorKind::InvalidIndex { container, index } => write!(f, "can't index {container} with {index}"),
+            ErrorKind::IndexOutOfBounds { index, len } => write!(f, "index {index} is out of bounds for length {len}"),
+            ErrorKind::UnhashableKey(ty) => write!(f, "a value of type {ty} can't be used as a hash key"),
+            ErrorKind::InvalidArgument { function, expected, found } => write!(f, "`{function}` expects {expected}, got {found}"),
+            ErrorKind::StackOverflow { limit } => write!(f, "stack overflow: calls nested more than {limit} deep"),
+            ErrorKind::Output(e) => write!(f, "failed to write output: {e}"),
+        }
+    }
+}
@@ -1,46 +1,847 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::rc::Rc;
+
 use crate::ast::ast::{self, Expression};
+use crate::eval::builtins;
+use crate::eval::environment::{Env, Environment};
+use crate::eval::error::{ErrorKind, RuntimeError};
+use crate::objects::bigint::BigInt;
 use crate::objects::objects;
+use crate::token::{self, Span};
+
+/// Why evaluation stopped short of producing a value: either a `return`
+/// unwinding to the enclosing function or program, or an error.
+enum Unwind {
+    Return(objects::Object),
+    Error(RuntimeError),
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(e: RuntimeError) -> Self {
+        Unwind::Error(e)
+    }
+}
+
+type EvalResult = Result<objects::Object, Unwind>;
+
+/// Watches evaluation: called once each expression is done with how deeply
+/// it was nested and what it gave. A `return` unwinding through the
+/// expression shows up as the value being returned.
+pub type Tracer = Box<dyn FnMut(usize, &ast::Expression, Result<&objects::Object, &RuntimeError>)>;
+
+/// How many calls may be nested before evaluation gives up with
+/// `StackOverflow`. A call takes 10-25 KB of the Rust stack in a debug
+/// build, the more the deeper it sits inside an expression, and 2-5 KB in
+/// a release one. This keeps a debug build inside the 2 MB of a spawned
+/// thread for simple recursion and well inside an 8 MB main thread.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 100;
+
+/// Where `puts` writes. It's shared so the host can keep a handle and
+/// read back what was written.
+pub type Output = Rc<RefCell<dyn Write>>;
 
-pub fn eval_program(program:Vec<ast::Statement>) -> objects::Object {
+/// What an evaluation carries along besides the scope.
+pub struct Context {
+    /// Calls currently in progress.
+    depth: usize,
+    pub max_call_depth: usize,
+    pub out: Output,
+    pub tracer: Option<Tracer>,
+    /// Expressions currently being evaluated, counted only while tracing.
+    nesting: usize,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Context {
+            depth: 0,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            out: Rc::new(RefCell::new(io::stdout())),
+            tracer: None,
+            nesting: 0,
+        }
+    }
+}
+
+pub fn eval_program(program:&[ast::Statement], env:&Env, ctx:&mut Context) -> Result<objects::Object, RuntimeError> {
     let mut result: objects::Object = objects::Object::Null;
     for s in program{
-        result = eval_stmnt(s); 
+        result = match eval_stmnt(s, env, ctx) {
+            Ok(v) => v,
+            Err(Unwind::Return(v)) => return Ok(v),
+            Err(Unwind::Error(e)) => return Err(e)
+        }; 
     }
-    result
+    Ok(result)
 }
 
-fn eval_stmnt(stmnt:ast::Statement) -> objects::Object {
+fn eval_stmnt(stmnt:&ast::Statement, env:&Env, ctx:&mut Context) -> EvalResult {
     match stmnt {
-        ast::Statement::ExprsStatement { token: _, exprs } => eval_exprs(exprs),
-        _ => objects::Object::Null
+        ast::Statement::ExprsStatement { exprs, .. } => eval_exprs(exprs, env, ctx),
+        ast::Statement::Let { ident, exprs, .. } => {
+            let val = eval_exprs(exprs, env, ctx)?;
+            env.borrow_mut().set(ident.to_string(), val);
+            Ok(objects::Object::Null)
+        },
+        ast::Statement::Return { exprs, .. } => Err(Unwind::Return(eval_exprs(exprs, env, ctx)?))
+    }
+}
+
+fn eval_exprs(s:&ast::Expression, env:&Env, ctx:&mut Context) -> EvalResult {
+    if ctx.tracer.is_none(){
+        return eval_exprs_untraced(s, env, ctx)
+    }
+    let depth = ctx.nesting;
+    ctx.nesting += 1;
+    let result = eval_exprs_untraced(s, env, ctx);
+    ctx.nesting = depth;
+    let outcome = match &result {
+        Ok(v) | Err(Unwind::Return(v)) => Ok(v),
+        Err(Unwind::Error(e)) => Err(e)
+    };
+    if let Some(tracer) = ctx.tracer.as_mut(){
+        tracer(depth, s, outcome)
     }
+    result
 }
 
-fn eval_exprs(s:ast::Expression) -> objects::Object  {
+fn eval_exprs_untraced(s:&ast::Expression, env:&Env, ctx:&mut Context) -> EvalResult {
+    // the bigger arms live in their own functions: in debug builds every
+    // local here would otherwise be paid for at each level of nesting
     match s {
-        ast::Expression::Int(s) => objects::Object::Int(s),
-        ast::Expression::Boolean{token: _, value} => objects::Object::Bool(value),
-        ast::Expression::InfixExprsn { left, right, oprt } => eval_infix(*left, *right, oprt),
-        _ =>  objects::Object::Null
-    }
-}
-
-fn eval_infix(left:ast::Expression, right:Expression, oprtr:String) -> objects::Object {
-        let right = eval_exprs(right);
-        let left = eval_exprs(left);
-        match (right, left) {
-            (objects::Object::Int(a), objects::Object::Int(b)) => {
-                match oprtr.as_str(){
-                    "+" => objects::Object::Int(a + b),
-                    "-" => objects::Object::Int(a - b),
-                    "*" => objects::Object::Int(a * b),
-                    "/" => objects::Object::Int(a / b),
-                    _ => objects::Object::Null
-                }
+        ast::Expression::Int(s, _) => Ok(objects::Object::Int(*s)),
+        ast::Expression::BigInt(s, _) => Ok(objects::Object::BigInt(s.clone())),
+        ast::Expression::Float(s, _) => Ok(objects::Object::Float(*s)),
+        ast::Expression::Str(s, _) => Ok(objects::Object::Str(s.clone())),
+        ast::Expression::Interpolated { parts, .. } => eval_interpolated(parts, env, ctx),
+        ast::Expression::Boolean{value, ..} => Ok(objects::Object::Bool(*value)),
+        ast::Expression::Ident(name, span) => eval_ident(name, *span, env, ctx),
+        ast::Expression::PrefixExprsn { token, exprsn, span } => eval_prefix(token, exprsn, *span, env, ctx),
+        ast::Expression::InfixExprsn { left, right, oprt, span } => eval_infix(left, right, oprt, *span, env, ctx),
+        ast::Expression::IfExprsn { condt, conseq, alter, .. } => {
+            if eval_exprs(condt, env, ctx)?.is_truthy(){
+                eval_block(conseq, env, ctx)
+            } else {
+                // a missing else branch parses as an empty block, giving null
+                eval_block(alter, env, ctx)
+            }
+        },
+        ast::Expression::FnExprsn { params, body, .. } => Ok(make_function(params, body, env)),
+        ast::Expression::Call { function, args, span } => eval_call(function, args, *span, env, ctx),
+        ast::Expression::Array { elements, .. } => Ok(objects::Object::Array(eval_list(elements, env, ctx)?)),
+        ast::Expression::Index { left, index, span } => eval_index_exprsn(left, index, *span, env, ctx),
+        ast::Expression::Hash { pairs, .. } => eval_hash(pairs, env, ctx),
+    }
+}
+
+fn make_function(params:&[ast::Expression], body:&Rc<Vec<ast::Statement>>, env:&Env) -> objects::Object {
+    objects::Object::Function {
+        params: params.iter().map(|p| p.to_string()).collect(),
+        body: Rc::clone(body),
+        env: Rc::clone(env)
+    }
+}
+
+fn eval_interpolated(parts:&[ast::Expression], env:&Env, ctx:&mut Context) -> EvalResult {
+    let mut out = String::new();
+    for part in parts{
+        out.push_str(&eval_exprs(part, env, ctx)?.to_string());
+    }
+    Ok(objects::Object::Str(out))
+}
+
+fn eval_list(exprs:&[ast::Expression], env:&Env, ctx:&mut Context) -> Result<Vec<objects::Object>, Unwind> {
+    let mut values = Vec::with_capacity(exprs.len());
+    for e in exprs{
+        values.push(eval_exprs(e, env, ctx)?);
+    }
+    Ok(values)
+}
+
+fn eval_call(function:&ast::Expression, args:&[ast::Expression], span:Span, env:&Env, ctx:&mut Context) -> EvalResult {
+    let function = eval_exprs(function, env, ctx)?;
+    let args = eval_list(args, env, ctx)?;
+    apply_function(function, args, span, ctx)
+}
+
+fn eval_index_exprsn(left:&ast::Expression, index:&ast::Expression, span:Span, env:&Env, ctx:&mut Context) -> EvalResult {
+    let left = eval_exprs(left, env, ctx)?;
+    let index_span = index.span();
+    let index = eval_exprs(index, env, ctx)?;
+    match left {
+        objects::Object::Hash(pairs) => eval_hash_index(&pairs, index, index_span),
+        left => eval_index(left, index, span)
+    }
+}
+
+fn eval_hash(pairs:&[(ast::Expression, ast::Expression)], env:&Env, ctx:&mut Context) -> EvalResult {
+    let mut hash = BTreeMap::new();
+    for (key, value) in pairs{
+        let key_span = key.span();
+        let key = eval_exprs(key, env, ctx)?;
+        let key = key.hash_key().ok_or_else(|| RuntimeError::new(ErrorKind::UnhashableKey(key.type_name()), key_span))?;
+        hash.insert(key, eval_exprs(value, env, ctx)?);
+    }
+    Ok(objects::Object::Hash(hash))
+}
+
+/// Looks a key up in a hash, giving `null` when it's missing.
+fn eval_hash_index(pairs:&BTreeMap<objects::HashKey, objects::Object>, key:objects::Object, span:Span) -> EvalResult {
+    match key.hash_key() {
+        Some(key) => Ok(pairs.get(&key).cloned().unwrap_or(objects::Object::Null)),
+        None => Err(RuntimeError::new(ErrorKind::UnhashableKey(key.type_name()), span).into())
+    }
+}
+
+/// Arrays take int indices; negative ones count back from the end, so
+/// `-1` is the last element.
+fn eval_index(left:objects::Object, index:objects::Object, span:Span) -> EvalResult {
+    let items = match left {
+        objects::Object::Array(items) => items,
+        other => return Err(RuntimeError::new(ErrorKind::NotIndexable(other.type_name()), span).into())
+    };
+    let out_of_bounds = |index:&objects::Object| RuntimeError::new(
+        ErrorKind::IndexOutOfBounds { index: index.to_string(), len: items.len() },
+        span
+    );
+    let i = match index {
+        objects::Object::Int(i) => i,
+        objects::Object::BigInt(_) => return Err(out_of_bounds(&index).into()),
+        other => return Err(RuntimeError::new(ErrorKind::InvalidIndex { container: "array", index: other.type_name() }, span).into())
+    };
+    let pos = if i < 0 { i.checked_add(items.len() as i64) } else { Some(i) };
+    match pos.and_then(|p| usize::try_from(p).ok()).and_then(|p| items.get(p)) {
+        Some(v) => Ok(v.clone()),
+        None => Err(out_of_bounds(&index).into())
+    }
+}
+
+fn eval_block(stmnts:&[ast::Statement], env:&Env, ctx:&mut Context) -> EvalResult {
+    let mut result = objects::Object::Null;
+    for s in stmnts{
+        result = eval_stmnt(s, env, ctx)?;
+    }
+    Ok(result)
+}
+
+fn apply_function(function:objects::Object, args:Vec<objects::Object>, span:Span, ctx:&mut Context) -> EvalResult {
+    let (params, body, env) = match function {
+        objects::Object::Function { params, body, env } => (params, body, env),
+        objects::Object::Builtin(native) => return native.call(&args).map_err(|e| RuntimeError::new(e.kind, span).into()),
+        other => return Err(RuntimeError::new(ErrorKind::NotCallable(other.type_name()), span).into())
+    };
+    if params.len() != args.len(){
+        return Err(RuntimeError::new(ErrorKind::WrongArgCount { expected: params.len(), found: args.len() }, span).into())
+    }
+    if ctx.depth >= ctx.max_call_depth{
+        return Err(RuntimeError::new(ErrorKind::StackOverflow { limit: ctx.max_call_depth }, span).into())
+    }
+    let scope = Environment::new_enclosed(env);
+    for (name, arg) in params.into_iter().zip(args){
+        scope.borrow_mut().set(name, arg);
+    }
+    ctx.depth += 1;
+    let result = eval_block(&body, &scope, ctx);
+    ctx.depth -= 1;
+    match result {
+        Err(Unwind::Return(v)) => Ok(v),
+        other => other
+    }
+}
+
+fn eval_ident(name:&str, span:Span, env:&Env, ctx:&Context) -> EvalResult {
+    match env.borrow().get(name) {
+        Some(v) => Ok(v),
+        None => builtins::lookup(name, &ctx.out).ok_or_else(|| RuntimeError::new(ErrorKind::UnknownIdentifier(name.to_string()), span).into())
+    }
+}
+
+fn eval_prefix(oprt:&token::Token, right:&ast::Expression, span:Span, env:&Env, ctx:&mut Context) -> EvalResult {
+    let right = eval_exprs(right, env, ctx)?;
+    apply_prefix(oprt, right, span)
+}
+
+fn apply_prefix(oprt:&token::Token, right:objects::Object, span:Span) -> EvalResult {
+    match (oprt, right) {
+        (token::Token::Bang, right) => Ok(objects::Object::Bool(!right.is_truthy())),
+        (token::Token::Minus, objects::Object::Int(v)) => match v.checked_neg() {
+            Some(v) => Ok(objects::Object::Int(v)),
+            None => Ok(objects::Object::from_bigint(-&BigInt::from(v)))
+        },
+        (token::Token::Minus, objects::Object::BigInt(v)) => Ok(objects::Object::from_bigint(-&v)),
+        (token::Token::Minus, objects::Object::Float(v)) => Ok(objects::Object::Float(-v)),
+        (oprt, right) => Err(RuntimeError::new(
+            ErrorKind::UnsupportedOperand { oprt: oprt.to_string(), operand: right.type_name() },
+            span
+        ).into())
+    }
+}
+
+fn eval_infix(left:&ast::Expression, right:&Expression, oprtr:&str, span:Span, env:&Env, ctx:&mut Context) -> EvalResult {
+    let left = eval_exprs(left, env, ctx)?;
+    let right = eval_exprs(right, env, ctx)?;
+    apply_infix(left, right, oprtr, span)
+}
+
+fn apply_infix(left:objects::Object, right:objects::Object, oprtr:&str, span:Span) -> EvalResult {
+    if let (objects::Object::Int(a), objects::Object::Int(b)) = (&left, &right){
+        return eval_int_infix(*a, *b, oprtr, span)
+    }
+    if let (objects::Object::Str(a), objects::Object::Str(b)) = (&left, &right){
+        return eval_string_infix(a, b, oprtr, span)
+    }
+    if let (Some(a), Some(b)) = (left.to_bigint(), right.to_bigint()){
+        return eval_bigint_infix(&a, &b, oprtr, span)
+    }
+    // a float on either side turns the other number into a float too
+    if let (Some(a), Some(b)) = (left.to_f64(), right.to_f64()){
+        return eval_float_infix(a, b, oprtr, (left.type_name(), right.type_name()), span)
+    }
+    let kind = match (&left, &right) {
+        _ if oprtr == "==" || oprtr == "!=" => match left.equals(&right) {
+            Some(eq) => return Ok(objects::Object::Bool(eq == (oprtr == "=="))),
+            None => ErrorKind::UnknownOperator { left: left.type_name(), oprt: oprtr.to_string(), right: right.type_name() }
+        },
+        _ if left.type_name() != right.type_name() => {
+            ErrorKind::TypeMismatch { left: left.type_name(), oprt: oprtr.to_string(), right: right.type_name() }
+        },
+        _ => ErrorKind::UnknownOperator { left: left.type_name(), oprt: oprtr.to_string(), right: right.type_name() }
+    };
+    Err(RuntimeError::new(kind, span).into())
+}
+
+/// Integer arithmetic never overflows: a result that doesn't fit in an
+/// `i64` is redone with `BigInt`s. A zero divisor raises `DivisionByZero`,
+/// `/` truncates toward zero and `%` takes the sign of the left operand.
+fn eval_int_infix(a:i64, b:i64, oprtr:&str, span:Span) -> EvalResult {
+    let checked = match oprtr{
+        "+" => a.checked_add(b),
+        "-" => a.checked_sub(b),
+        "*" => a.checked_mul(b),
+        "/" | "%" if b == 0 => return Err(RuntimeError::new(ErrorKind::DivisionByZero, span).into()),
+        "/" => a.checked_div(b),
+        "%" => a.checked_rem(b),
+        _ => return eval_comparison(&a, &b, oprtr, ("int", "int"), span)
+    };
+    match checked {
+        Some(v) => Ok(objects::Object::Int(v)),
+        None => eval_bigint_infix(&BigInt::from(a), &BigInt::from(b), oprtr, span)
+    }
+}
+
+fn eval_bigint_infix(a:&BigInt, b:&BigInt, oprtr:&str, span:Span) -> EvalResult {
+    let result = match oprtr{
+        "+" => a + b,
+        "-" => a - b,
+        "*" => a * b,
+        "/" | "%" => match a.div_rem(b) {
+            Some((q, _)) if oprtr == "/" => q,
+            Some((_, r)) => r,
+            None => return Err(RuntimeError::new(ErrorKind::DivisionByZero, span).into())
+        },
+        _ => return eval_comparison(a, b, oprtr, ("int", "int"), span)
+    };
+    Ok(objects::Object::from_bigint(result))
+}
+
+fn eval_string_infix(a:&str, b:&str, oprtr:&str, span:Span) -> EvalResult {
+    match oprtr{
+        "+" => Ok(objects::Object::Str(format!("{a}{b}"))),
+        "==" => Ok(objects::Object::Bool(a == b)),
+        "!=" => Ok(objects::Object::Bool(a != b)),
+        _ => Err(RuntimeError::new(ErrorKind::UnknownOperator { left: "string", oprt: oprtr.to_string(), right: "string" }, span).into())
+    }
+}
+
+/// Float arithmetic follows IEEE 754, except that a zero divisor raises
+/// `DivisionByZero` just like it does for ints.
+fn eval_float_infix(a:f64, b:f64, oprtr:&str, types:(&'static str, &'static str), span:Span) -> EvalResult {
+    let result = match oprtr{
+        "+" => a + b,
+        "-" => a - b,
+        "*" => a * b,
+        "/" | "%" if b == 0.0 => return Err(RuntimeError::new(ErrorKind::DivisionByZero, span).into()),
+        "/" => a / b,
+        "%" => a % b,
+        _ => return eval_comparison(&a, &b, oprtr, types, span)
+    };
+    Ok(objects::Object::Float(result))
+}
+
+/// `types` names the operands' types for the error an unknown operator
+/// raises.
+fn eval_comparison<T: PartialOrd>(a:&T, b:&T, oprtr:&str, types:(&'static str, &'static str), span:Span) -> EvalResult {
+    let result = match oprtr{
+        "<" => objects::Object::Bool(a < b),
+        ">" => objects::Object::Bool(a > b),
+        "==" => objects::Object::Bool(a == b),
+        "!=" => objects::Object::Bool(a != b),
+        _ => return Err(RuntimeError::new(ErrorKind::UnknownOperator { left: types.0, oprt: oprtr.to_string(), right: types.1 }, span).into())
+    };
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::eval::{apply_infix, eval_program, Context, Tracer, Unwind, DEFAULT_MAX_CALL_DEPTH};
+    use crate::eval::{Environment, RuntimeError};
+    use crate::eval::error::ErrorKind;
+    use crate::lexer::Lexer;
+    use crate::objects::objects::Object;
+    use crate::parser::Parser;
+    use crate::token::token::Span;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn eval(src:&str) -> Result<Object, RuntimeError>{
+        let mut p = Parser::new(Box::new(Lexer::new(src.to_string())));
+        let program = p.parse_program().unwrap();
+        eval_program(&program, &Environment::new(), &mut Context::default())
+    }
+
+    #[test]
+    fn test_let_bindings(){
+        let cases = [
+            ("let a = 5; a;", "5"),
+            ("let a = 5 * 5; a;", "25"),
+            ("let a = 5; let b = a; b;", "5"),
+            ("let a = 5; let b = a; let c = a * b * 2; c;", "50"),
+            ("let a = 1; let a = true; a;", "true"),
+        ];
+        for (src, expected) in cases{
+            assert_eq!(eval(src).unwrap().to_string(), expected, "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_unknown_identifier(){
+        let err = eval("let a = 1;\na * b;").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnknownIdentifier("b".to_string()));
+        assert_eq!((err.span.line, err.span.col), (2, 5));
+        assert_eq!(err.to_string(), "unknown identifier `b`");
+    }
+
+    #[test]
+    fn test_functions(){
+        let cases = [
+            ("let identity = fn(x) { x; }; identity(5);", "5"),
+            ("let double = fn(x) { x * 2; }; double(5);", "10"),
+            ("let add = fn(x, y) { x + y; }; add(5 + 5, add(5, 5));", "20"),
+            ("fn(x) { x; }(5)", "5"),
+            ("let f = fn() { 7 }; f();", "7"),
+            ("fn(a, b) { a }", "fn(a, b)"),
+        ];
+        for (src, expected) in cases{
+            assert_eq!(eval(src).unwrap().to_string(), expected, "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_closures_and_higher_order(){
+        let src = "
+            let adder = fn(x) { fn(y) { x + y } };
+            let addTwo = adder(2);
+            let twice = fn(f, x) { f(f(x)) };
+            let x = 100;
+            twice(addTwo, 3);
+        ";
+        assert_eq!(eval(src).unwrap().to_string(), "7");
+    }
+
+    #[test]
+    fn test_function_bodies_are_shared(){
+        let made = eval("let make = fn() { fn(x) { x } }; [make(), make()]").unwrap();
+        match made {
+            Object::Array(fns) => match (&fns[0], &fns[1]) {
+                (Object::Function { body: a, .. }, Object::Function { body: b, .. }) => assert!(Rc::ptr_eq(a, b)),
+                other => panic!("expected two functions, got {other:?}")
             },
-            _ => {
-                println!("oops mismatched types");
-                objects::Object::Null
+            other => panic!("expected an array, got {other}")
+        }
+    }
+
+    #[test]
+    fn test_call_errors(){
+        let err = eval("let a = 1; a(2);").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::NotCallable("int"));
+        let err = eval("let f = fn(x) { x }; f(1, 2);").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::WrongArgCount { expected: 1, found: 2 });
+        let err = eval("let g = fn() { let z = 1; z }; g(); z;").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnknownIdentifier("z".to_string()));
+    }
+
+    #[test]
+    fn test_call_depth_limit(){
+        let mut p = Parser::new(Box::new(Lexer::new("let f = fn(n) { if n == 0 { return 0; } 1 + f(n - 1) };".to_string())));
+        let env = Environment::new();
+        let mut ctx = Context { max_call_depth: 10, ..Context::default() };
+        eval_program(&p.parse_program().unwrap(), &env, &mut ctx).unwrap();
+        let mut call = |src:&str| {
+            let mut p = Parser::new(Box::new(Lexer::new(src.to_string())));
+            eval_program(&p.parse_program().unwrap(), &env, &mut ctx)
+        };
+        assert_eq!(call("f(9)").unwrap().to_string(), "9");
+        let err = call("f(10)").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::StackOverflow { limit: 10 });
+        assert_eq!(err.to_string(), "stack overflow: calls nested more than 10 deep");
+        // the depth unwinds with the error
+        assert_eq!(call("f(9)").unwrap().to_string(), "9");
+    }
+
+    #[test]
+    fn test_default_call_depth_fits_a_thread(){
+        // a crash here takes down the whole test binary instead of failing
+        let err = std::thread::spawn(|| {
+            eval("let f = fn(n) { if n == 0 { return 0; } 1 + f(n - 1) }; f(100000)").unwrap_err()
+        }).join().unwrap();
+        assert_eq!(err.kind, ErrorKind::StackOverflow { limit: DEFAULT_MAX_CALL_DEPTH });
+    }
+
+    #[test]
+    fn test_if_else(){
+        let cases = [
+            ("if true { 10 }", "10"),
+            ("if false { 10 }", "null"),
+            ("if 1 { 10 }", "10"),
+            ("if 0 { 10 } else { 20 }", "10"),
+            ("let n = if false { 1 }; if n { 1 } else { 2 }", "2"),
+            ("if true { 1; 2; 3 } else { 4 }", "3"),
+            ("if false { 10 } else { 20 }", "20"),
+            ("if true { } else { 20 }", "null"),
+            ("let x = 5; if x { if false { 1 } else { x * 2 } }", "10"),
+        ];
+        for (src, expected) in cases{
+            assert_eq!(eval(src).unwrap().to_string(), expected, "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_return(){
+        let cases = [
+            ("return 10; 9;", "10"),
+            ("9; return 2 * 5; 9;", "10"),
+            ("if true { if true { return 10; } return 1; }", "10"),
+            ("let f = fn(x) { if true { if true { return x; } } return 0; }; f(7) + 1;", "8"),
+            ("let f = fn() { let g = fn() { return 1; }; g(); return 2; }; f();", "2"),
+            ("let f = fn(x) { let y = if true { return x; }; 0 }; f(3);", "3"),
+        ];
+        for (src, expected) in cases{
+            assert_eq!(eval(src).unwrap().to_string(), expected, "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_prefix(){
+        let cases = [
+            ("!true", "false"),
+            ("!false", "true"),
+            ("!5", "false"),
+            ("!!5", "true"),
+            ("!0", "false"),
+            ("let n = if false { 1 }; !n", "true"),
+            ("-5", "-5"),
+            ("--5", "5"),
+            ("-1 + 2", "1"),
+            ("let f = fn() { 3 }; -f()", "-3"),
+        ];
+        for (src, expected) in cases{
+            assert_eq!(eval(src).unwrap().to_string(), expected, "{}", src);
         }
+        let err = eval("-true").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnsupportedOperand { oprt: "-".to_string(), operand: "bool" });
+        assert_eq!(err.to_string(), "unsupported operand type for `-`: bool");
+    }
+
+    #[test]
+    fn test_arithmetic(){
+        let cases = [
+            ("10 - 3", "7"),
+            ("10 / 2", "5"),
+            ("7 / 2", "3"),
+            ("2 - 3 - 4", "-5"),
+            ("100 / 10 / 5", "2"),
+            ("2 * (3 + 4) - -1", "15"),
+            ("((1 + 2) * 2) / 6;", "1"),
+        ];
+        for (src, expected) in cases{
+            assert_eq!(eval(src).unwrap().to_string(), expected, "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_comparison_and_equality(){
+        let cases = [
+            ("1 < 2", "true"),
+            ("1 > 2", "false"),
+            ("2 > 2", "false"),
+            ("1 == 1", "true"),
+            ("1 != 1", "false"),
+            ("1 + 2 == 3", "true"),
+            ("1 < 2 == true", "true"),
+            ("2 * 3 != 6", "false"),
+            ("true == true", "true"),
+            ("true != false", "true"),
+            ("1 == true", "false"),
+            ("1 != true", "true"),
+            ("let n = if false { 1 }; n == n", "true"),
+            ("let n = if false { 1 }; n == false", "false"),
+            ("let n = if false { 1 }; 0 != n", "true"),
+        ];
+        for (src, expected) in cases{
+            assert_eq!(eval(src).unwrap().to_string(), expected, "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_runtime_errors(){
+        let cases = [
+            ("5 + true;", ErrorKind::TypeMismatch { left: "int", oprt: "+".to_string(), right: "bool" }),
+            ("5 + true; 5;", ErrorKind::TypeMismatch { left: "int", oprt: "+".to_string(), right: "bool" }),
+            ("true > false", ErrorKind::UnknownOperator { left: "bool", oprt: ">".to_string(), right: "bool" }),
+            ("if 10 > 1 { true + false; 10 }", ErrorKind::UnknownOperator { left: "bool", oprt: "+".to_string(), right: "bool" }),
+            ("let f = fn() { 1 }; f == f", ErrorKind::UnknownOperator { left: "function", oprt: "==".to_string(), right: "function" }),
+            ("let f = fn(x) { return x / 0; }; f(1); 2", ErrorKind::DivisionByZero),
+        ];
+        for (src, expected) in cases{
+            assert_eq!(eval(src).unwrap_err().kind, expected, "{}", src);
+        }
+        let err = eval("let x = 1;\nlet y = x + (true + 1);").unwrap_err();
+        assert_eq!((err.span.line, err.span.col, err.span.end - err.span.start), (2, 14, 8));
+        assert_eq!(err.to_string(), "type mismatch: bool + int");
+    }
+
+    #[test]
+    fn test_checked_arithmetic(){
+        let cases = [
+            ("7 % 3", "1"),
+            ("-7 % 3", "-1"),
+            ("7 % -3", "1"),
+            ("-7 / 2", "-3"),
+            ("1 + 10 % 4 * 2", "5"),
+            ("2147483647 + 1", "2147483648"),
+            ("9223372036854775807", "9223372036854775807"),
+            ("-9223372036854775807 - 1", "-9223372036854775808"),
+        ];
+        for (src, expected) in cases{
+            assert_eq!(eval(src).unwrap().to_string(), expected, "{}", src);
+        }
+        let errors = [
+            ("1 / 0", ErrorKind::DivisionByZero),
+            ("1 % 0", ErrorKind::DivisionByZero),
+            ("99999999999999999999 / 0", ErrorKind::DivisionByZero),
+            ("(9223372036854775807 + 1) % 0", ErrorKind::DivisionByZero),
+        ];
+        for (src, expected) in errors{
+            assert_eq!(eval(src).unwrap_err().kind, expected, "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_bigint_promotion(){
+        let cases = [
+            ("9223372036854775807 + 1", "9223372036854775808"),
+            ("-9223372036854775807 - 2", "-9223372036854775809"),
+            ("4294967296 * 4294967296", "18446744073709551616"),
+            ("let min = -9223372036854775807 - 1; -min", "9223372036854775808"),
+            ("let min = -9223372036854775807 - 1; min / -1", "9223372036854775808"),
+            ("let min = -9223372036854775807 - 1; min % -1", "0"),
+            ("123456789012345678901234567890", "123456789012345678901234567890"),
+            ("-123456789012345678901234567890 / 10", "-12345678901234567890123456789"),
+            ("(9223372036854775807 + 1) - 1 == 9223372036854775807", "true"),
+            ("99999999999999999999 > 1", "true"),
+            ("-99999999999999999999 < -1", "true"),
+            ("let big = 99999999999999999999; big == big + 0", "true"),
+            ("
+                let fact = fn(n) { if n < 2 { return 1; } n * fact(n - 1) };
+                fact(30);
+            ", "265252859812191058636308480000000"),
+        ];
+        for (src, expected) in cases{
+            assert_eq!(eval(src).unwrap().to_string(), expected, "{}", src);
+        }
+        let err = eval("99999999999999999999 + true").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TypeMismatch { left: "int", oprt: "+".to_string(), right: "bool" });
+    }
+
+    #[test]
+    fn test_floats(){
+        let cases = [
+            ("1.5", "1.5"),
+            ("1.5e3", "1500.0"),
+            ("2.0", "2.0"),
+            ("-0.25", "-0.25"),
+            ("1e20", "1e20"),
+            ("1.5 + 1.5", "3.0"),
+            ("1 + 0.5", "1.5"),
+            ("0.5 * 4", "2.0"),
+            ("7 / 2.0", "3.5"),
+            ("7.5 % 2", "1.5"),
+            ("99999999999999999999 * 1.0", "1e20"),
+            ("1 == 1.0", "true"),
+            ("1.0 != 1", "false"),
+            ("0.1 + 0.2 == 0.3", "false"),
+            ("2 < 2.5", "true"),
+            ("2.5 > 3", "false"),
+            ("let half = fn(x) { x / 2.0 }; half(5)", "2.5"),
+        ];
+        for (src, expected) in cases{
+            assert_eq!(eval(src).unwrap().to_string(), expected, "{}", src);
+        }
+        assert_eq!(eval("1.0 / 0").unwrap_err().kind, ErrorKind::DivisionByZero);
+        assert_eq!(eval("1 % 0.0").unwrap_err().kind, ErrorKind::DivisionByZero);
+        let err = eval("1.5 + true").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TypeMismatch { left: "float", oprt: "+".to_string(), right: "bool" });
+        // the parser has no other operators, but the error names the real types
+        match apply_infix(Object::Float(1.5), Object::Int(2), "&", Span::default()) {
+            Err(Unwind::Error(e)) => assert_eq!(e.kind, ErrorKind::UnknownOperator { left: "float", oprt: "&".to_string(), right: "int" }),
+            _ => panic!("expected an unknown operator error"),
+        }
+    }
+
+    #[test]
+    fn test_strings(){
+        let cases = [
+            ("\"hello world\"", "hello world"),
+            ("\"hello\" + \" \" + \"world\"", "hello world"),
+            ("let greet = fn(name) { \"hi, \" + name }; greet(\"bob\")", "hi, bob"),
+            ("\"a\\tb\"", "a\tb"),
+            ("\"abc\" == \"abc\"", "true"),
+            ("\"abc\" != \"abd\"", "true"),
+            ("\"1\" == 1", "false"),
+            ("if \"\" { 1 } else { 2 }", "1"),
+        ];
+        for (src, expected) in cases{
+            assert_eq!(eval(src).unwrap().to_string(), expected, "{}", src);
+        }
+        let err = eval("\"a\" - \"b\"").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnknownOperator { left: "string", oprt: "-".to_string(), right: "string" });
+        let err = eval("\"a\" + 1").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TypeMismatch { left: "string", oprt: "+".to_string(), right: "int" });
+    }
+
+    #[test]
+    fn test_interpolation(){
+        let cases = [
+            ("let name = \"ann\"; let age = 41; \"hello ${name}, you are ${age + 1}\"", "hello ann, you are 42"),
+            ("\"${1.5} ${true} ${if false { 1 }} ${fn(x) { x }}\"", "1.5 true null fn(x)"),
+            ("let f = fn(x) { \"<${x}>\" }; \"${f(\"${1 + 1}\")}\"", "<2>"),
+            ("\"${99999999999999999999 + 1}\"", "100000000000000000000"),
+            ("\"a\\${b}\"", "a${b}"),
+        ];
+        for (src, expected) in cases{
+            assert_eq!(eval(src).unwrap().to_string(), expected, "{}", src);
+        }
+        let err = eval("\"x = ${x}\"").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnknownIdentifier("x".to_string()));
+        assert_eq!(err.span.col, 8);
+    }
+
+    #[test]
+    fn test_arrays(){
+        let cases = [
+            ("[1, 2 * 2, 3 + 3]", "[1, 4, 6]"),
+            ("[]", "[]"),
+            ("[\"a, b\", [true, if false { 1 }], 1.5]", "[\"a, b\", [true, null], 1.5]"),
+            ("[1, 2, 3][0]", "1"),
+            ("[1, 2, 3][1 + 1]", "3"),
+            ("let a = [1, 2, 3]; a[0] + a[1] + a[2]", "6"),
+            ("let i = 0; [1][i]", "1"),
+            ("[1, 2, 3][-1]", "3"),
+            ("[1, 2, 3][-3]", "1"),
+            ("[[1, 2], [3]][0][1]", "2"),
+            ("let get = fn() { [fn(x) { x * 10 }] }; get()[0](4)", "40"),
+            ("[1, [2]] == [1, [2]]", "true"),
+            ("[1, 2] != [1, 2, 3]", "true"),
+            ("\"${[1, \"x\"]}\"", "[1, \"x\"]"),
+        ];
+        for (src, expected) in cases{
+            assert_eq!(eval(src).unwrap().to_string(), expected, "{}", src);
+        }
+        let errors = [
+            ("[1, 2, 3][3]", ErrorKind::IndexOutOfBounds { index: "3".to_string(), len: 3 }),
+            ("[1, 2, 3][-4]", ErrorKind::IndexOutOfBounds { index: "-4".to_string(), len: 3 }),
+            ("[][0]", ErrorKind::IndexOutOfBounds { index: "0".to_string(), len: 0 }),
+            ("[1][99999999999999999999]", ErrorKind::IndexOutOfBounds { index: "99999999999999999999".to_string(), len: 1 }),
+            ("[1][true]", ErrorKind::InvalidIndex { container: "array", index: "bool" }),
+            ("1[0]", ErrorKind::NotIndexable("int")),
+        ];
+        for (src, expected) in errors{
+            assert_eq!(eval(src).unwrap_err().kind, expected, "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_hashes(){
+        let cases = [
+            ("{\"a\": 1, 2: true}", "{2: true, \"a\": 1}"),
+            ("{}", "{}"),
+            ("let key = \"k\"; {key + \"1\": [1], false: {}}[\"k1\"]", "[1]"),
+            ("{\"a\": 1, \"a\": 2}[\"a\"]", "2"),
+            ("{1: \"one\", true: \"yes\"}[1]", "one"),
+            ("{1: \"one\", true: \"yes\"}[true]", "yes"),
+            ("{\"a\": 1}[\"b\"]", "null"),
+            ("{99999999999999999999: 1}[99999999999999999998 + 1]", "1"),
+            ("let config = {\"db\": {\"port\": 5432}}; config[\"db\"][\"port\"]", "5432"),
+            ("{\"a\": 1, \"b\": [2]} == {\"b\": [2], \"a\": 1}", "true"),
+            ("{\"a\": 1} == {\"a\": 2}", "false"),
+        ];
+        for (src, expected) in cases{
+            assert_eq!(eval(src).unwrap().to_string(), expected, "{}", src);
+        }
+        let errors = [
+            ("{[1]: 2}", ErrorKind::UnhashableKey("array")),
+            ("{1.5: 2}", ErrorKind::UnhashableKey("float")),
+            ("{\"a\": 1}[fn() { 1 }]", ErrorKind::UnhashableKey("function")),
+        ];
+        for (src, expected) in errors{
+            assert_eq!(eval(src).unwrap_err().kind, expected, "{}", src);
+        }
+        let err = eval("let h = {};\nh[{}]").unwrap_err();
+        assert_eq!((err.span.line, err.span.col), (2, 3));
+        assert_eq!(err.to_string(), "a value of type hash can't be used as a hash key");
+    }
+
+    #[test]
+    fn test_tracer(){
+        let lines = Rc::new(RefCell::new(vec![]));
+        let sink = Rc::clone(&lines);
+        let tracer: Tracer = Box::new(move |depth, exprs, result| {
+            let result = match result {
+                Ok(v) => v.to_string(),
+                Err(e) => format!("error: {e}")
+            };
+            sink.borrow_mut().push(format!("{}{exprs} => {result}", "  ".repeat(depth)));
+        });
+        let mut ctx = Context { tracer: Some(tracer), ..Context::default() };
+        let env = Environment::new();
+        let traced = |src:&str, ctx:&mut Context| {
+            let mut p = Parser::new(Box::new(Lexer::new(src.to_string())));
+            eval_program(&p.parse_program().unwrap(), &env, ctx)
+        };
+        let ok = traced("let f = fn(x) { x * 2 };\nf(1 + 2)", &mut ctx).map(|v| v.to_string());
+        let err = traced("-true", &mut ctx).unwrap_err();
+        assert_eq!(ok.unwrap(), "6");
+        assert_eq!(err.kind, ErrorKind::UnsupportedOperand { oprt: "-".to_string(), operand: "bool" });
+        let expected = [
+            "fn(x) { (x * 2) } => fn(x)",
+            "  f => fn(x)",
+            "    1 => 1",
+            "    2 => 2",
+            "  (1 + 2) => 3",
+            "    x => 3",
+            "    2 => 2",
+            "  (x * 2) => 6",
+            "f((1 + 2)) => 6",
+            "  true => true",
+            "(-true) => error: unsupported operand type for `-`: bool",
+        ];
+        assert_eq!(*lines.borrow(), expected);
+
+        // with no tracer installed nothing is recorded
+        ctx.tracer = None;
+        traced("1 + 1", &mut ctx).unwrap();
+        assert_eq!(lines.borrow().len(), expected.len());
     }
 }
@@ -1 +1,8 @@
+pub mod builtins;
+pub mod environment;
+pub mod error;
 pub mod eval;
+
+pub use environment::Environment;
+pub use error::{ErrorKind, RuntimeError};
+pub use eval::{Output, Tracer};
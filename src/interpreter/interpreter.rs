@@ -0,0 +1,483 @@
+use crate::diagnostics::Diagnostic;
+use crate::eval::environment::Env;
+use crate::eval::error::ErrorKind;
+use crate::eval::eval::{eval_program, Context, Output, Tracer};
+use crate::eval::{Environment, RuntimeError};
+use crate::lexer::Lexer;
+use crate::objects::convert::{FromObject, IntoObject};
+use crate::objects::objects::{NativeFn, NativeResult, Object};
+use crate::parser::parser::ParseError;
+use crate::parser::Parser;
+use std::fmt;
+use std::path::Path;
+
+/// Why evaluating some source failed.
+#[derive(Debug)]
+pub enum Error {
+    /// The source file couldn't be read.
+    Io(std::io::Error),
+    /// Every syntax error in the source; nothing was evaluated.
+    Parse(Vec<ParseError>),
+    Runtime(RuntimeError),
+}
+
+impl Error {
+    /// Renders the error as diagnostics pointing into `source`, the text
+    /// that was evaluated.
+    pub fn render(&self, file_name: &str, source: &str, colour: bool) -> String {
+        match self {
+            Error::Parse(errors) => errors.iter().map(|e| Diagnostic::from(e).render(file_name, source, colour)).collect(),
+            Error::Runtime(e) => Diagnostic::from(e).render(file_name, source, colour),
+            Error::Io(e) => format!("error: {file_name}: {e}\n"),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(errors) => {
+                for (i, e) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{e}")?;
+                }
+                Ok(())
+            }
+            Error::Runtime(e) => write!(f, "{e}"),
+            Error::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// The type a native function wants for one of its arguments.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ParamType {
+    Any,
+    Int,
+    Float,
+    /// An int or a float.
+    Number,
+    Str,
+    Bool,
+    Array,
+    Hash,
+    /// A Monkey function or a builtin.
+    Function,
+}
+
+impl ParamType {
+    pub fn accepts(&self, obj: &Object) -> bool {
+        match self {
+            ParamType::Any => true,
+            ParamType::Number => matches!(obj, Object::Int(_) | Object::BigInt(_) | Object::Float(_)),
+            ParamType::Int => obj.type_name() == "int",
+            ParamType::Float => obj.type_name() == "float",
+            ParamType::Str => obj.type_name() == "string",
+            ParamType::Bool => obj.type_name() == "bool",
+            ParamType::Array => obj.type_name() == "array",
+            ParamType::Hash => obj.type_name() == "hash",
+            ParamType::Function => obj.type_name() == "function",
+        }
+    }
+
+    /// How the type is described in error messages.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ParamType::Any => "any value",
+            ParamType::Int => "int",
+            ParamType::Float => "float",
+            ParamType::Number => "number",
+            ParamType::Str => "string",
+            ParamType::Bool => "bool",
+            ParamType::Array => "array",
+            ParamType::Hash => "hash",
+            ParamType::Function => "function",
+        }
+    }
+}
+
+/// What a typed native function may return: any value that converts
+/// into an object, or a `Result` of one to raise a runtime error.
+pub trait IntoNativeResult {
+    fn into_native_result(self) -> NativeResult;
+}
+
+impl<T: IntoObject> IntoNativeResult for T {
+    fn into_native_result(self) -> NativeResult {
+        Ok(self.into_object())
+    }
+}
+
+impl<T: IntoObject> IntoNativeResult for Result<T, RuntimeError> {
+    fn into_native_result(self) -> NativeResult {
+        self.map(IntoObject::into_object)
+    }
+}
+
+/// A Rust function whose parameters are read out of the call's arguments
+/// with [`FromObject`]. `Args` is the tuple of its parameter types.
+pub trait TypedFn<Args> {
+    fn arity(&self) -> usize;
+
+    /// Calls the function; `args` must already be `arity` long.
+    fn call(&self, name: &str, args: &[Object]) -> NativeResult;
+}
+
+macro_rules! impl_typed_fn {
+    ($($arg:ident $idx:tt),*) => {
+        impl<F, R, $($arg),*> TypedFn<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R,
+            R: IntoNativeResult,
+            $($arg: FromObject,)*
+        {
+            fn arity(&self) -> usize {
+                <[usize]>::len(&[$($idx),*])
+            }
+
+            #[allow(unused_variables)]
+            fn call(&self, name: &str, args: &[Object]) -> NativeResult {
+                self($(
+                    $arg::from_object(&args[$idx]).map_err(|e| RuntimeError::at_call(ErrorKind::InvalidArgument {
+                        function: name.to_string(),
+                        expected: e.expected,
+                        found: e.found,
+                    }))?
+                ),*).into_native_result()
+            }
+        }
+    };
+}
+
+impl_typed_fn!();
+impl_typed_fn!(A 0);
+impl_typed_fn!(A 0, B 1);
+impl_typed_fn!(A 0, B 1, C 2);
+impl_typed_fn!(A 0, B 1, C 2, D 3);
+impl_typed_fn!(A 0, B 1, C 2, D 3, E 4);
+
+/// Runs Monkey source against a global scope that lasts as long as the
+/// interpreter, so bindings made by one evaluation are seen by the next.
+pub struct Interpreter {
+    env: Env,
+    ctx: Context,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Interpreter { env: Environment::new(), ctx: Context::default() }
+    }
+
+    /// Parses and evaluates `src`, giving the value of its last statement.
+    pub fn eval_str(&mut self, src: &str) -> Result<Object, Error> {
+        let mut parser = Parser::new(Box::new(Lexer::new(src.to_string())));
+        let program = parser.parse_program().map_err(Error::Parse)?;
+        eval_program(&program, &self.env, &mut self.ctx).map_err(Error::Runtime)
+    }
+
+    /// Reads the file at `path` and evaluates it like [`Interpreter::eval_str`].
+    pub fn eval_file(&mut self, path: impl AsRef<Path>) -> Result<Object, Error> {
+        let src = std::fs::read_to_string(path).map_err(Error::Io)?;
+        self.eval_str(&src)
+    }
+
+    /// Limits how deeply calls may nest; going deeper raises a
+    /// `StackOverflow` error. Each level needs up to about 25 KB of stack
+    /// in a debug build and 5 KB in a release one, so raise the limit only
+    /// on a thread with the stack to match.
+    pub fn set_max_call_depth(&mut self, depth: usize) {
+        self.ctx.max_call_depth = depth;
+    }
+
+    /// Sends what `puts` prints to `out` rather than stdout. A write that
+    /// fails becomes a runtime error.
+    pub fn set_output(&mut self, out: Output) {
+        self.ctx.out = out;
+    }
+
+    /// Installs `tracer` to watch every expression this interpreter
+    /// evaluates, giving back the one it replaces. `None` turns tracing off.
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) -> Option<Tracer> {
+        std::mem::replace(&mut self.ctx.tracer, tracer)
+    }
+
+    /// Binds `name` in the global scope, replacing any earlier binding.
+    pub fn set_global(&mut self, name: &str, value: impl IntoObject) {
+        self.env.borrow_mut().set(name.to_string(), value.into_object());
+    }
+
+    /// The value `name` is bound to in the global scope. Builtins that
+    /// haven't been shadowed aren't bindings, so they give `None`.
+    pub fn get_global(&self, name: &str) -> Option<Object> {
+        self.env.borrow().get(name)
+    }
+
+    /// Makes a Rust closure callable from scripts as `name`. Calls with
+    /// anything but `arity` arguments fail before `f` runs.
+    pub fn register_fn<F>(&mut self, name: &str, arity: usize, f: F)
+    where
+        F: Fn(&[Object]) -> NativeResult + 'static,
+    {
+        self.register_typed_fn(name, &vec![ParamType::Any; arity], f)
+    }
+
+    /// Like [`Interpreter::register_fn`], but each argument must also be
+    /// of the matching type in `params`.
+    pub fn register_typed_fn<F>(&mut self, name: &str, params: &[ParamType], f: F)
+    where
+        F: Fn(&[Object]) -> NativeResult + 'static,
+    {
+        let params = params.to_vec();
+        let fn_name = name.to_string();
+        let native = NativeFn::new(name, move |args| {
+            if args.len() != params.len() {
+                return Err(RuntimeError::at_call(ErrorKind::WrongArgCount { expected: params.len(), found: args.len() }));
+            }
+            for (param, arg) in params.iter().zip(args) {
+                if !param.accepts(arg) {
+                    return Err(RuntimeError::at_call(ErrorKind::InvalidArgument {
+                        function: fn_name.clone(),
+                        expected: param.name(),
+                        found: arg.type_name(),
+                    }));
+                }
+            }
+            f(args)
+        });
+        self.set_global(name, Object::Builtin(native));
+    }
+
+    /// Makes a Rust function with ordinary typed parameters callable from
+    /// scripts. Each argument is converted with [`FromObject`], and a call
+    /// with the wrong count or a value that doesn't convert fails before
+    /// `f` runs.
+    pub fn register_native<Args, F>(&mut self, name: &str, f: F)
+    where
+        F: TypedFn<Args> + 'static,
+    {
+        let fn_name = name.to_string();
+        self.register_fn(name, f.arity(), move |args| f.call(&fn_name, args));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::error::ErrorKind;
+    use crate::eval::RuntimeError;
+    use crate::interpreter::{Error, Interpreter, ParamType};
+    use crate::objects::convert::FromObject;
+    use crate::objects::objects::Object;
+    use crate::parser::parser::{ParseErrorKind, MAX_NESTING};
+    use std::cell::{Cell, RefCell};
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    fn runtime_error(err: Error) -> RuntimeError {
+        match err {
+            Error::Runtime(e) => e,
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_register_fn() {
+        let mut interp = Interpreter::new();
+        let calls = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&calls);
+        interp.register_fn("add", 2, move |args| {
+            counter.set(counter.get() + 1);
+            match (&args[0], &args[1]) {
+                (Object::Int(a), Object::Int(b)) => Ok(Object::Int(a + b)),
+                _ => Ok(Object::Null),
+            }
+        });
+        assert_eq!(interp.eval_str("add(1, 2)").unwrap().to_string(), "3");
+        assert_eq!(interp.eval_str("let twice = fn(f, x) { f(x, x) }; twice(add, 4)").unwrap().to_string(), "8");
+        assert_eq!(interp.eval_str("add").unwrap().to_string(), "builtin add");
+        assert_eq!(calls.get(), 2);
+
+        let err = runtime_error(interp.eval_str("add(1)").unwrap_err());
+        assert_eq!(err.kind, ErrorKind::WrongArgCount { expected: 2, found: 1 });
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_register_typed_fn() {
+        let mut interp = Interpreter::new();
+        interp.register_typed_fn("repeat", &[ParamType::Str, ParamType::Int], |args| match (&args[0], &args[1]) {
+            (Object::Str(s), Object::Int(n)) => Ok(Object::Str(s.repeat(*n as usize))),
+            _ => unreachable!(),
+        });
+        interp.register_typed_fn("half", &[ParamType::Number], |args| Ok(Object::Float(args[0].to_f64().unwrap() / 2.0)));
+        assert_eq!(interp.eval_str("repeat(\"ab\", 3)").unwrap().to_string(), "ababab");
+        assert_eq!(interp.eval_str("half(3) + half(1.0)").unwrap().to_string(), "2.0");
+
+        let err = runtime_error(interp.eval_str("\nrepeat(3, \"ab\")").unwrap_err());
+        assert_eq!(err.kind, ErrorKind::InvalidArgument { function: "repeat".to_string(), expected: "string", found: "int" });
+        assert_eq!((err.span.line, err.span.col), (2, 1));
+        let err = runtime_error(interp.eval_str("half(\"1\")").unwrap_err());
+        assert_eq!(err.to_string(), "`half` expects number, got string");
+    }
+
+    #[test]
+    fn test_native_errors_and_globals() {
+        let mut interp = Interpreter::new();
+        interp.register_fn("fail", 0, |_| Err(RuntimeError::at_call(ErrorKind::DivisionByZero)));
+        let err = runtime_error(interp.eval_str("let x = 1;\nx + fail()").unwrap_err());
+        assert_eq!(err.kind, ErrorKind::DivisionByZero);
+        assert_eq!((err.span.line, err.span.col), (2, 5));
+
+        // bindings outlive a single eval, and scripts can shadow host functions
+        interp.eval_str("let fail = 5;").unwrap();
+        assert_eq!(interp.eval_str("fail + x").unwrap().to_string(), "6");
+
+        match interp.eval_str("let = 1; let y 2;").unwrap_err() {
+            Error::Parse(errors) => assert_eq!(errors.len(), 2),
+            other => panic!("expected parse errors, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_globals() {
+        let mut interp = Interpreter::new();
+        interp.set_global("limit", Object::Int(10));
+        assert_eq!(interp.eval_str("let total = limit * 2; total").unwrap().to_string(), "20");
+        assert_eq!(interp.get_global("total").unwrap().to_string(), "20");
+        assert!(interp.get_global("missing").is_none());
+        assert!(interp.get_global("len").is_none());
+        // locals stay local
+        interp.eval_str("let f = fn() { let inner = 1; inner }; f()").unwrap();
+        assert!(interp.get_global("inner").is_none());
+    }
+
+    #[test]
+    fn test_set_output() {
+        let out = Rc::new(RefCell::new(vec![]));
+        let mut interp = Interpreter::new();
+        interp.set_output(out.clone());
+        interp.eval_str("puts(\"hello\", [1, 2])").unwrap();
+        assert_eq!(*out.borrow(), b"hello\n[1, 2]\n");
+    }
+
+    #[test]
+    fn test_set_tracer() {
+        let seen = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&seen);
+        let mut traced = Interpreter::new();
+        assert!(traced.set_tracer(Some(Box::new(move |_, _, _| counter.set(counter.get() + 1)))).is_none());
+        traced.eval_str("1 + 2").unwrap();
+        assert_eq!(seen.get(), 3);
+        // other interpreters aren't traced
+        Interpreter::new().eval_str("1 + 2").unwrap();
+        assert_eq!(seen.get(), 3);
+        assert!(traced.set_tracer(None).is_some());
+        traced.eval_str("1 + 2").unwrap();
+        assert_eq!(seen.get(), 3);
+    }
+
+    #[test]
+    fn test_max_call_depth() {
+        let mut interp = Interpreter::new();
+        interp.set_max_call_depth(10);
+        interp.eval_str("let loop = fn() { loop() };").unwrap();
+        match interp.eval_str("loop()") {
+            Err(Error::Runtime(e)) => assert_eq!(e.kind, ErrorKind::StackOverflow { limit: 10 }),
+            other => panic!("expected a stack overflow, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deep_nesting() {
+        let mut interp = Interpreter::new();
+        let n = MAX_NESTING - 1;
+        let src = format!("{}1{}", "(".repeat(n), ")".repeat(n));
+        assert_eq!(interp.eval_str(&src).unwrap().to_string(), "1");
+        // an `if` nests its block inside itself as well
+        let src = format!("{}1{}", "if (true) { ".repeat(n / 2), " }".repeat(n / 2));
+        assert_eq!(interp.eval_str(&src).unwrap().to_string(), "1");
+        assert_eq!(interp.eval_str(&format!("{}1", "-".repeat(n))).unwrap().to_string(), "-1");
+
+        for src in [format!("{}1", "(".repeat(2000)), format!("puts({}", "-".repeat(200_000))] {
+            match interp.eval_str(&src) {
+                Err(Error::Parse(errors)) => assert_eq!(errors[0].kind, ParseErrorKind::TooDeep),
+                other => panic!("expected a parse error, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_eval_file() {
+        let path = std::env::temp_dir().join(format!("monkey-eval-file-{}.mk", std::process::id()));
+        std::fs::write(&path, "let greeting = \"hi\";\ngreeting + \"!\"").unwrap();
+        let mut interp = Interpreter::new();
+        let result = interp.eval_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(result.unwrap().to_string(), "hi!");
+        assert_eq!(interp.get_global("greeting").unwrap().to_string(), "hi");
+        assert!(matches!(interp.eval_file(&path), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn test_register_native() {
+        let mut interp = Interpreter::new();
+        interp.register_native("add", |a: i64, b: i64| a + b);
+        interp.register_native("mean", |xs: Vec<f64>| if xs.is_empty() { None } else { Some(xs.iter().sum::<f64>() / xs.len() as f64) });
+        interp.register_native("lookup", |map: HashMap<String, i64>, key: String| map.get(&key).copied());
+        interp.register_native("split", |s: String| (s.len() as i64, s.to_uppercase()));
+        interp.register_native("answer", || 42i64);
+        interp.register_native("checkedDiv", |a: i64, b: i64| {
+            a.checked_div(b).ok_or_else(|| RuntimeError::at_call(ErrorKind::DivisionByZero))
+        });
+        let cases = [
+            ("add(1, 2)", "3"),
+            ("mean([1, 2.5, 3])", "2.1666666666666665"),
+            ("mean([])", "null"),
+            ("lookup({\"a\": 1}, \"a\")", "1"),
+            ("lookup({\"a\": 1}, \"b\")", "null"),
+            ("split(\"ab\")", "[2, \"AB\"]"),
+            ("answer()", "42"),
+            ("checkedDiv(7, 2)", "3"),
+        ];
+        for (src, expected) in cases {
+            assert_eq!(interp.eval_str(src).unwrap().to_string(), expected, "{}", src);
+        }
+
+        let errors = [
+            ("add(1)", ErrorKind::WrongArgCount { expected: 2, found: 1 }),
+            ("add(1, \"2\")", ErrorKind::InvalidArgument { function: "add".to_string(), expected: "64-bit int", found: "string" }),
+            ("add(1, 99999999999999999999)", ErrorKind::InvalidArgument { function: "add".to_string(), expected: "64-bit int", found: "int" }),
+            ("mean([1, true])", ErrorKind::InvalidArgument { function: "mean".to_string(), expected: "number", found: "bool" }),
+            ("lookup({1: 1}, \"a\")", ErrorKind::InvalidArgument { function: "lookup".to_string(), expected: "string", found: "int" }),
+            ("checkedDiv(1, 0)", ErrorKind::DivisionByZero),
+        ];
+        for (src, expected) in errors {
+            assert_eq!(runtime_error(interp.eval_str(src).unwrap_err()).kind, expected, "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_typed_globals() {
+        let mut interp = Interpreter::new();
+        interp.set_global("names", vec!["a".to_string(), "b".to_string()]);
+        interp.set_global("limits", HashMap::from([("max".to_string(), 3i64)]));
+        let result = interp.eval_str("push(names, \"c\")[limits[\"max\"] - 1]").unwrap();
+        assert_eq!(String::from_object(&result), Ok("c".to_string()));
+    }
+}
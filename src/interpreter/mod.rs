@@ -0,0 +1,3 @@
+pub mod interpreter;
+
+pub use interpreter::{Error, Interpreter, IntoNativeResult, ParamType, TypedFn};
@@ -5,35 +5,64 @@ pub struct Lexer{
    ch:u8,
    pos:usize,
    peek:usize,
+   // line and column of `ch`
+   line:usize,
+   col:usize,
+   // one entry per `${` we are inside of, counting the braces opened
+   // since, so we can tell which `}` resumes the string
+   interp:Vec<usize>,
 }
 
 
 impl Lexer {
     pub fn new(input:String) -> Self {
-        let mut l = Lexer{input:input.into_bytes(), ch:0, pos:0, peek:0};
+        let mut l = Lexer{input:input.into_bytes(), ch:0, pos:0, peek:0, line:1, col:0, interp:vec![]};
         l.read_char();
         l
     }
 
     fn read_char(&mut self){
+        if self.ch == b'\n'{
+            self.line += 1;
+            self.col = 0;
+        }
         if self.peek >= self.input.len(){
             self.ch = b'\0'
         } else{
             self.ch = self.input[self.peek]
         }
+        // continuation bytes of a multi-byte character share its column
+        if self.ch & 0xC0 != 0x80{
+            self.col += 1;
+        }
         self.pos = self.peek;
         self.peek +=1;
     }
 
 
+    fn peek_char(&self) -> u8{
+        if self.peek >= self.input.len(){
+            b'\0'
+        } else{
+            self.input[self.peek]
+        }
+    }
+
     fn skip_white(&mut self){
         while self.ch.is_ascii_whitespace(){
             self.read_char();
         }
     }
 
-    pub fn next_token(&mut self) -> token::Token{
+    pub fn next_token(&mut self) -> token::SpannedToken{
         self.skip_white();
+        let (start, line, col) = (self.pos, self.line, self.col);
+        let tok = self.read_token();
+        let end = self.pos.min(self.input.len());
+        token::SpannedToken { tok, span: token::Span::new(start, end, line, col) }
+    }
+
+    fn read_token(&mut self) -> token::Token{
         let tok = match self.ch{
             b'\0' => return token::Token::Eof,
             b'a'..=b'z' => {
@@ -78,7 +107,8 @@ impl Lexer {
                 token::Token::Lt
             }
             b'=' => {
-                if self.peek == b'='.into(){
+                if self.peek_char() == b'='{
+                    self.read_char();
                     token::Token::Eq
                 } else{
                     token::Token::Assing
@@ -87,27 +117,47 @@ impl Lexer {
             b',' => {
                 token::Token::Comma
             },
+            b':' => {
+                token::Token::Colon
+            },
             b'!' =>{
-                if self.peek == b'='.into(){
+                if self.peek_char() == b'='{
+                    self.read_char();
                     token::Token::NotEq
                 } else{
                     token::Token::Bang
                 }
             },
             b'0'..=b'9' => {
-                let number = self.read_number();
-                return token::Token::Int(number)
+                return self.read_number()
             },
             b'{' =>{
+                if let Some(depth) = self.interp.last_mut(){
+                    *depth += 1;
+                }
                 token::Token::Lbrace
             },
             b'}' =>{
+                match self.interp.last_mut(){
+                    Some(0) => {
+                        self.interp.pop();
+                        return self.read_string()
+                    },
+                    Some(depth) => *depth -= 1,
+                    None => {}
+                }
                 token::Token::Rbrace
             },
 
             b')' =>{
                 token::Token::Rparen
             },
+            b'[' =>{
+                token::Token::Lbracket
+            },
+            b']' =>{
+                token::Token::Rbracket
+            },
             b'(' =>{
                 token::Token::Lparen
             },
@@ -122,20 +172,138 @@ impl Lexer {
             },
             b'/' => {
                 token::Token::Slash
+            },
+            b'%' => {
+                token::Token::Percent
+            },
+            b'"' => {
+                return self.read_string()
+            }
+            _ => {
+                // take the whole character, not just its first byte
+                let start = self.pos;
+                while self.peek_char() & 0xC0 == 0x80{
+                    self.read_char();
+                }
+                token::Token::Illgl(String::from_utf8_lossy(&self.input[start..=self.pos]).to_string())
             }
-            _ => token::Token::Illgl((self.ch as char).to_string())
         };
         self.read_char();
-        return tok
+        tok
     }
 
 
-    fn read_number(&mut self) -> String{
+    /// Reads an integer, or a float when the digits are followed by a
+    /// fraction (`1.5`) and/or an exponent (`2e10`, `1.5E-3`).
+    fn read_number(&mut self) -> token::Token{
         let pos = self.pos;
+        let mut is_float = false;
+        self.read_digits();
+        if self.ch == b'.' && self.peek_char().is_ascii_digit(){
+            is_float = true;
+            self.read_char();
+            self.read_digits();
+        }
+        if self.ch == b'e' || self.ch == b'E'{
+            let sign = matches!(self.peek_char(), b'+' | b'-') as usize;
+            let after = self.input.get(self.peek + sign).copied().unwrap_or(b'\0');
+            if after.is_ascii_digit(){
+                is_float = true;
+                for _ in 0..=sign{
+                    self.read_char();
+                }
+                self.read_digits();
+            }
+        }
+        let literal = String::from_utf8_lossy(&self.input[pos..self.pos]).to_string();
+        if is_float{
+            token::Token::Float(literal)
+        } else{
+            token::Token::Int(literal)
+        }
+    }
+
+    /// Reads a double-quoted string, decoding the `\n`, `\t`, `\"`, `\\`,
+    /// `\$` and `\u{...}` escapes. A string with a bad escape comes back
+    /// as an illegal token and one that runs to the end of the input as an
+    /// `Unterminated` one, both holding the raw source.
+    ///
+    /// A `${` switches the lexer over to reading an embedded expression:
+    /// the text so far becomes a `StrHead` (or `StrMid` when `ch` is the
+    /// `}` that ended an earlier expression) and the matching `}` resumes
+    /// the string. The last piece of an interpolated string is a `StrTail`.
+    fn read_string(&mut self) -> token::Token{
+        let start = self.pos;
+        let resumed = self.ch == b'}';
+        let mut value: Vec<u8> = vec![];
+        let mut valid = true;
+        self.read_char();
+        while self.ch != b'"'{
+            if self.pos >= self.input.len(){
+                return token::Token::Unterminated(String::from_utf8_lossy(&self.input[start..]).to_string())
+            }
+            if self.ch == b'$' && self.peek_char() == b'{'{
+                self.read_char();
+                self.read_char();
+                self.interp.push(0);
+                let value = String::from_utf8_lossy(&value).to_string();
+                return if resumed { token::Token::StrMid(value) } else { token::Token::StrHead(value) }
+            }
+            if self.ch == b'\\'{
+                self.read_char();
+                match self.ch{
+                    b'n' => value.push(b'\n'),
+                    b't' => value.push(b'\t'),
+                    b'"' => value.push(b'"'),
+                    b'\\' => value.push(b'\\'),
+                    b'$' => value.push(b'$'),
+                    b'u' => match self.read_unicode_escape(){
+                        Some(c) => value.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                        None => valid = false
+                    },
+                    // leave the end of input to the loop
+                    b'\0' if self.pos >= self.input.len() => continue,
+                    _ => valid = false
+                }
+            } else{
+                value.push(self.ch);
+            }
+            self.read_char();
+        }
+        // step over the closing quote
+        self.read_char();
+        if !valid{
+            return token::Token::Illgl(String::from_utf8_lossy(&self.input[start..self.pos]).to_string())
+        }
+        let value = String::from_utf8_lossy(&value).to_string();
+        if resumed { token::Token::StrTail(value) } else { token::Token::Str(value) }
+    }
+
+    /// Reads the `{hex}` part of a `\u{...}` escape, leaving `ch` on the
+    /// closing brace.
+    fn read_unicode_escape(&mut self) -> Option<char>{
+        if self.peek_char() != b'{'{
+            return None
+        }
+        self.read_char();
+        let mut code: u32 = 0;
+        let mut digits = 0;
+        while digits < 6 && self.peek_char().is_ascii_hexdigit(){
+            self.read_char();
+            code = code * 16 + (self.ch as char).to_digit(16)?;
+            digits += 1;
+        }
+        if digits == 0 || self.peek_char() != b'}'{
+            return None
+        }
+        self.read_char();
+        char::from_u32(code)
+    }
+
+    fn read_digits(&mut self){
         while self.ch.is_ascii_digit(){
             self.read_char();
         }
-        return String::from_utf8_lossy(&self.input[pos..self.pos]).to_string();
     }
 
     fn read_word(&mut self) -> String{
@@ -143,7 +311,7 @@ impl Lexer {
         while self.ch.is_ascii_alphabetic(){
             self.read_char();
         }
-        return String::from_utf8_lossy(&self.input[pos..self.pos]).to_string();
+        String::from_utf8_lossy(&self.input[pos..self.pos]).to_string()
     }
 
 }
@@ -160,12 +328,91 @@ mod tests {
         Token::Minus
         ];
         for tok in expected{
-            assert_eq!(new_lexer.next_token(), tok);
+            assert_eq!(new_lexer.next_token().tok, tok);
         }
     }
-}
 
+    #[test]
+    fn test_two_char_operators(){
+        let mut lexer = Lexer::new(String::from("a == b != !c = d"));
+        let expected = [Token::Ident(String::from("a")), Token::Eq, Token::Ident(String::from("b")), Token::NotEq,
+        Token::Bang, Token::Ident(String::from("c")), Token::Assing, Token::Ident(String::from("d")), Token::Eof
+        ];
+        for tok in expected{
+            assert_eq!(lexer.next_token().tok, tok);
+        }
+    }
 
+    #[test]
+    fn test_numbers(){
+        let mut lexer = Lexer::new(String::from("12 1.5 1.5e3 2E-2 7e+1 3. 4e x"));
+        let expected = [Token::Int(String::from("12")), Token::Float(String::from("1.5")), Token::Float(String::from("1.5e3")),
+        Token::Float(String::from("2E-2")), Token::Float(String::from("7e+1")), Token::Int(String::from("3")), Token::Illgl(String::from(".")),
+        Token::Int(String::from("4")), Token::Ident(String::from("e")), Token::Ident(String::from("x")), Token::Eof
+        ];
+        for tok in expected{
+            assert_eq!(lexer.next_token().tok, tok);
+        }
+    }
 
+    #[test]
+    fn test_strings(){
+        let src = r#""hello" "a\tb\nc" "say \"hi\"" "back\\slash" "\u{48}\u{e9}\u{1F600}" "" "héllo" "bad \q" "open\"#;
+        let mut lexer = Lexer::new(String::from(src));
+        let expected = [Token::Str(String::from("hello")), Token::Str(String::from("a\tb\nc")), Token::Str(String::from("say \"hi\"")),
+        Token::Str(String::from("back\\slash")), Token::Str(String::from("Hé😀")), Token::Str(String::new()), Token::Str(String::from("héllo")),
+        Token::Illgl(String::from("\"bad \\q\"")), Token::Unterminated(String::from("\"open\\")), Token::Eof
+        ];
+        for tok in expected{
+            assert_eq!(lexer.next_token().tok, tok);
+        }
+    }
+
+    #[test]
+    fn test_interpolation(){
+        let src = r#""hello ${name}, you are ${age + 1}" "${ fn(){ "${x}" }() }!" "cost: \${x}""#;
+        let mut lexer = Lexer::new(String::from(src));
+        let expected = [Token::StrHead(String::from("hello ")), Token::Ident(String::from("name")), Token::StrMid(String::from(", you are ")),
+        Token::Ident(String::from("age")), Token::Plus, Token::Int(String::from("1")), Token::StrTail(String::new()),
+        Token::StrHead(String::new()), Token::Func, Token::Lparen, Token::Rparen, Token::Lbrace, Token::StrHead(String::new()),
+        Token::Ident(String::from("x")), Token::StrTail(String::new()), Token::Rbrace, Token::Lparen, Token::Rparen, Token::StrTail(String::from("!")),
+        Token::Str(String::from("cost: ${x}")), Token::Eof
+        ];
+        for tok in expected{
+            assert_eq!(lexer.next_token().tok, tok);
+        }
+    }
+
+    #[test]
+    fn test_brackets(){
+        let mut lexer = Lexer::new(String::from("[1, a][0]"));
+        let expected = [Token::Lbracket, Token::Int(String::from("1")), Token::Comma, Token::Ident(String::from("a")), Token::Rbracket,
+        Token::Lbracket, Token::Int(String::from("0")), Token::Rbracket, Token::Eof
+        ];
+        for tok in expected{
+            assert_eq!(lexer.next_token().tok, tok);
+        }
+    }
 
+    #[test]
+    fn test_spans(){
+        let mut lexer = Lexer::new(String::from("let x\n  = 42;"));
+        let expected = [(0, 3, 1, 1), (4, 5, 1, 5), (8, 9, 2, 3), (10, 12, 2, 5), (12, 13, 2, 7), (13, 13, 2, 8)];
+        for (start, end, line, col) in expected{
+            let span = lexer.next_token().span;
+            assert_eq!((span.start, span.end, span.line, span.col), (start, end, line, col));
+        }
+    }
 
+    #[test]
+    fn test_illegal_characters(){
+        let mut lexer = Lexer::new(String::from("é @ 日本"));
+        let expected = [("é", 0, 2, 1), ("@", 3, 4, 3), ("日", 5, 8, 5), ("本", 8, 11, 6)];
+        for (raw, start, end, col) in expected{
+            let token = lexer.next_token();
+            assert_eq!(token.tok, Token::Illgl(String::from(raw)));
+            assert_eq!((token.span.start, token.span.end, token.span.col), (start, end, col));
+        }
+        assert_eq!(lexer.next_token().tok, Token::Eof);
+    }
+}
@@ -0,0 +1,25 @@
+#![allow(clippy::module_inception)]
+//! An interpreter for the Monkey language.
+//!
+//! ```
+//! use monkey::Interpreter;
+//!
+//! let mut interp = Interpreter::new();
+//! interp.eval_str("let double = fn(x) { x * 2 };").unwrap();
+//! assert_eq!(interp.eval_str("double(21)").unwrap().to_string(), "42");
+//! ```
+
+pub mod ast;
+pub mod diagnostics;
+pub mod eval;
+pub mod interpreter;
+pub mod lexer;
+pub mod objects;
+pub mod parser;
+pub mod repl;
+pub mod token;
+
+pub use eval::RuntimeError;
+pub use interpreter::{Error, Interpreter, ParamType};
+pub use objects::convert::{ConversionError, FromObject, IntoObject};
+pub use objects::objects::Object;
@@ -1,18 +1,255 @@
-use eval::eval::eval_program;
-
-mod eval;
-mod lexer;
-mod objects;
-mod token;
-mod ast;
-mod parser;
-fn main() {
-    let src = String::from("
-        ((1 + 2) * 2) / 6;
-        ");
-    let lexer = lexer::Lexer::new(src);
-    let mut parser = parser::Parser::new(Box::new(lexer));
-    let statmnts = parser.parse_program();
-    let result = eval_program(statmnts);
-    println!("{}", result)
+use monkey::ast::ast;
+use monkey::eval::Output;
+use monkey::lexer::Lexer;
+use monkey::parser::Parser;
+use monkey::token::Token;
+use monkey::{repl, Error, Interpreter};
+use std::cell::RefCell;
+use std::io::{IsTerminal, Write};
+use std::process::ExitCode;
+use std::rc::Rc;
+
+// exit codes follow sysexits.h
+const EXIT_USAGE: u8 = 64;
+const EXIT_PARSE: u8 = 65;
+const EXIT_NO_INPUT: u8 = 66;
+const EXIT_RUNTIME: u8 = 70;
+
+/// Stack for the thread that runs scripts. A call takes up to about
+/// 25 KB of it in debug builds, so it has room for `MAX_CALL_DEPTH` calls
+/// with plenty to spare.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+const MAX_CALL_DEPTH: usize = 4000;
+
+const USAGE: &str = "\
+usage: monkey [run [--tokens] [--ast] [--trace] <script> [args...]]
+
+  --tokens  print the script's tokens with their positions instead of running it
+  --ast     print the script's syntax tree instead of running it
+  --trace   log every expression evaluated, and its value, to stderr";
+
+/// What `run` should do besides, or instead of, running the script.
+#[derive(Default)]
+struct DebugModes {
+    tokens: bool,
+    ast: bool,
+    trace: bool,
+}
+
+fn main() -> ExitCode {
+    std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run_main)
+        .expect("failed to spawn the interpreter thread")
+        .join()
+        .unwrap_or(ExitCode::FAILURE)
+}
+
+fn run_main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let colour = std::io::stderr().is_terminal();
+    match args.split_first() {
+        None => {
+            let stdin = std::io::stdin();
+            let prompt = stdin.is_terminal();
+            match repl::run(interpreter(), stdin.lock(), Rc::new(RefCell::new(std::io::stdout())), &mut std::io::stderr(), prompt, colour){
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    ExitCode::FAILURE
+                }
+            }
+        },
+        Some((cmd, rest)) if cmd == "run" => {
+            let mut modes = DebugModes::default();
+            let mut rest = rest;
+            while let Some((flag, tail)) = rest.split_first().filter(|(a, _)| a.starts_with("--")) {
+                match flag.as_str() {
+                    "--tokens" => modes.tokens = true,
+                    "--ast" => modes.ast = true,
+                    "--trace" => modes.trace = true,
+                    _ => return usage_error(&format!("unknown option `{flag}`")),
+                }
+                rest = tail;
+            }
+            match rest.split_first() {
+                Some((path, script_args)) => {
+                    let code = run_script(path, script_args, &modes, Rc::new(RefCell::new(std::io::stdout())), &mut std::io::stderr(), colour);
+                    ExitCode::from(code)
+                },
+                None => usage_error("`run` needs a script to run"),
+            }
+        },
+        Some((cmd, _)) if cmd == "-h" || cmd == "--help" => {
+            println!("{USAGE}");
+            ExitCode::SUCCESS
+        },
+        Some((cmd, _)) => usage_error(&format!("unknown command `{cmd}`")),
+    }
+}
+
+/// An interpreter that makes use of the big stack.
+fn interpreter() -> Interpreter {
+    let mut interp = Interpreter::new();
+    interp.set_max_call_depth(MAX_CALL_DEPTH);
+    interp
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("error: {message}\n{USAGE}");
+    ExitCode::from(EXIT_USAGE)
+}
+
+/// Runs the script at `path` with `args` bound to the `args` global,
+/// printing to `out` and reporting any error to `err`. With `--tokens` or
+/// `--ast` the script is dumped to `out` instead. Gives the code to exit
+/// with.
+fn run_script(path: &str, args: &[String], modes: &DebugModes, out: Output, err: &mut impl Write, colour: bool) -> u8 {
+    let src = match std::fs::read_to_string(path) {
+        Ok(src) => src,
+        Err(e) => {
+            let _ = write!(err, "{}", Error::Io(e).render(path, "", colour));
+            return EXIT_NO_INPUT
+        }
+    };
+    if modes.tokens || modes.ast {
+        return dump(path, &src, modes, &mut *out.borrow_mut(), err, colour)
+    }
+    let mut interp = interpreter();
+    interp.set_output(out);
+    if modes.trace {
+        interp.set_tracer(Some(Box::new(|depth, exprs, result| {
+            let span = exprs.span();
+            let result = match result {
+                Ok(v) => v.to_string(),
+                Err(e) => format!("error: {e}"),
+            };
+            eprintln!("{}{}:{} {exprs} => {result}", "  ".repeat(depth), span.line, span.col);
+        })));
+    }
+    interp.set_global("args", args.to_vec());
+    match interp.eval_str(&src) {
+        Ok(_) => 0,
+        Err(e) => {
+            let _ = write!(err, "{}", e.render(path, &src, colour));
+            match e {
+                Error::Parse(_) => EXIT_PARSE,
+                Error::Runtime(_) => EXIT_RUNTIME,
+                Error::Io(_) => EXIT_NO_INPUT,
+            }
+        }
+    }
+}
+
+fn dump(path: &str, src: &str, modes: &DebugModes, out: &mut dyn Write, err: &mut impl Write, colour: bool) -> u8 {
+    if modes.tokens {
+        let mut lexer = Lexer::new(src.to_string());
+        loop {
+            let token = lexer.next_token();
+            let _ = writeln!(out, "{}:{}\t{:?}", token.span.line, token.span.col, token.tok);
+            if token.tok == Token::Eof {
+                break
+            }
+        }
+    }
+    if modes.ast {
+        let mut parser = Parser::new(Box::new(Lexer::new(src.to_string())));
+        match parser.parse_program() {
+            Ok(program) => {
+                let _ = write!(out, "{}", ast::tree(&program));
+            },
+            Err(errors) => {
+                let _ = write!(err, "{}", Error::Parse(errors).render(path, src, colour));
+                return EXIT_PARSE
+            }
+        }
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{run_script, DebugModes, EXIT_NO_INPUT, EXIT_PARSE, EXIT_RUNTIME};
+    use std::cell::RefCell;
+    use std::io::{self, Write};
+    use std::rc::Rc;
+
+    fn run_with(name: &str, src: &str, args: &[&str], modes: &DebugModes) -> (u8, String, String) {
+        let path = std::env::temp_dir().join(format!("monkey-{name}-{}.mk", std::process::id()));
+        std::fs::write(&path, src).unwrap();
+        let (out, mut err) = (Rc::new(RefCell::new(vec![])), vec![]);
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        let code = run_script(path.to_str().unwrap(), &args, modes, out.clone(), &mut err, false);
+        std::fs::remove_file(&path).unwrap();
+        let err = String::from_utf8(err).unwrap().replace(path.to_str().unwrap(), "script.mk");
+        let out = out.borrow().clone();
+        (code, String::from_utf8(out).unwrap(), err)
+    }
+
+    fn run(name: &str, src: &str, args: &[&str]) -> (u8, String) {
+        let (code, _, err) = run_with(name, src, args, &DebugModes::default());
+        (code, err)
+    }
+
+    /// Fails every write, like stdout does once the reader of a pipe exits.
+    struct ClosedPipe;
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_run_script() {
+        let (code, out, err) = run_with("ok", "let n = len(args);\nif n == 2 { puts(args[1]) }", &["a", "b"], &DebugModes::default());
+        assert_eq!((code, out.as_str(), err.as_str()), (0, "b\n", ""));
+
+        let (code, err) = run("runtime", "let x = args[0];", &[]);
+        assert_eq!(code, EXIT_RUNTIME);
+        assert!(err.starts_with("error: index 0 is out of bounds for length 0\n --> script.mk:1:9\n"), "{}", err);
+
+        let (code, err) = run("parse", "let = 1;\nlet y 2;", &[]);
+        assert_eq!(code, EXIT_PARSE);
+        assert_eq!(err.matches("error: ").count(), 2, "{}", err);
+    }
+
+    #[test]
+    fn test_closed_output() {
+        let path = std::env::temp_dir().join(format!("monkey-closed-{}.mk", std::process::id()));
+        std::fs::write(&path, "puts(1);").unwrap();
+        let mut err = vec![];
+        let code = run_script(path.to_str().unwrap(), &[], &DebugModes::default(), Rc::new(RefCell::new(ClosedPipe)), &mut err, false);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(code, EXIT_RUNTIME);
+        let err = String::from_utf8(err).unwrap();
+        assert!(err.starts_with("error: failed to write output: "), "{}", err);
+    }
+
+    #[test]
+    fn test_missing_script() {
+        let mut err = vec![];
+        assert_eq!(run_script("/no/such/script.mk", &[], &DebugModes::default(), Rc::new(RefCell::new(vec![])), &mut err, false), EXIT_NO_INPUT);
+        assert!(String::from_utf8(err).unwrap().starts_with("error: /no/such/script.mk: "));
+    }
+
+    #[test]
+    fn test_dumps() {
+        let tokens = DebugModes { tokens: true, ..DebugModes::default() };
+        let (code, out, _) = run_with("tokens", "let x = 1;\nputs(x);", &[], &tokens);
+        assert_eq!(code, 0);
+        assert!(out.starts_with("1:1\tLet\n1:5\tIdent(\"x\")\n1:7\tAssing\n1:9\tInt(\"1\")\n1:10\tSemicolon\n2:1\tIdent(\"puts\")\n"), "{}", out);
+        assert!(out.ends_with("2:8\tSemicolon\n2:9\tEof\n"), "{}", out);
+
+        let ast = DebugModes { ast: true, ..DebugModes::default() };
+        let (code, out, _) = run_with("ast", "let x = 1;\nputs(x);", &[], &ast);
+        assert_eq!((code, out.as_str()), (0, "Let x @1:1\n  Int 1 @1:9\nCall @2:1\n  Ident puts @2:1\n  Ident x @2:6\n"));
+        let (code, out, err) = run_with("ast-error", "let = 1;", &[], &ast);
+        assert_eq!((code, out.as_str()), (EXIT_PARSE, ""));
+        assert!(err.starts_with("error: expected an identifier"), "{}", err);
+    }
 }
@@ -0,0 +1,342 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// An arbitrary-precision signed integer.
+///
+/// The magnitude is stored as base 2^32 limbs, least significant first,
+/// with no zero limbs at the top; zero has no limbs and is never negative.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    mag: Vec<u32>,
+}
+
+impl BigInt {
+    fn from_parts(negative: bool, mut mag: Vec<u32>) -> Self {
+        trim(&mut mag);
+        let negative = negative && !mag.is_empty();
+        BigInt { negative, mag }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mag.is_empty()
+    }
+
+    /// Parses an optionally `-` prefixed run of decimal digits.
+    pub fn parse(s: &str) -> Option<BigInt> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let mut mag = vec![];
+        for b in digits.bytes() {
+            mul_small_add(&mut mag, 10, (b - b'0') as u32);
+        }
+        Some(BigInt::from_parts(negative, mag))
+    }
+
+    /// The value as an `i64`, if it fits.
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.mag.len() > 2 {
+            return None;
+        }
+        let mut v: i128 = 0;
+        for limb in self.mag.iter().rev() {
+            v = (v << 32) | *limb as i128;
+        }
+        if self.negative {
+            v = -v;
+        }
+        i64::try_from(v).ok()
+    }
+
+    /// The nearest `f64`, which may lose precision or be infinite.
+    pub fn to_f64(&self) -> f64 {
+        let v = self.mag.iter().rev().fold(0.0, |acc, limb| acc * 4294967296.0 + *limb as f64);
+        if self.negative { -v } else { v }
+    }
+
+    /// Truncating division: the quotient rounds toward zero and the
+    /// remainder takes the sign of `self`. `None` when `other` is zero.
+    pub fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        if other.is_zero() {
+            return None;
+        }
+        let (q, r) = div_rem_mag(&self.mag, &other.mag);
+        Some((BigInt::from_parts(self.negative != other.negative, q), BigInt::from_parts(self.negative, r)))
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(v: i64) -> Self {
+        let abs = v.unsigned_abs();
+        BigInt::from_parts(v < 0, vec![abs as u32, (abs >> 32) as u32])
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_mag(&self.mag, &other.mag),
+            (true, true) => cmp_mag(&other.mag, &self.mag),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_parts(self.negative, add_mag(&self.mag, &other.mag));
+        }
+        match cmp_mag(&self.mag, &other.mag) {
+            Ordering::Less => BigInt::from_parts(other.negative, sub_mag(&other.mag, &self.mag)),
+            _ => BigInt::from_parts(self.negative, sub_mag(&self.mag, &other.mag)),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &(-other)
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::from_parts(self.negative != other.negative, mul_mag(&self.mag, &other.mag))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.mag.clone())
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // peel off nine decimal digits at a time, lowest first
+        let mut chunks = vec![];
+        let mut mag = self.mag.clone();
+        while !mag.is_empty() {
+            let (q, r) = div_rem_small(&mag, 1_000_000_000);
+            chunks.push(r);
+            mag = q;
+        }
+        if self.negative {
+            write!(f, "-")?;
+        }
+        let mut chunks = chunks.iter().rev();
+        if let Some(first) = chunks.next() {
+            write!(f, "{first}")?;
+        }
+        for chunk in chunks {
+            write!(f, "{chunk:09}")?;
+        }
+        Ok(())
+    }
+}
+
+fn trim(mag: &mut Vec<u32>) {
+    while mag.last() == Some(&0) {
+        mag.pop();
+    }
+}
+
+fn cmp_mag(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut out = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0u64;
+    for i in 0..a.len().max(b.len()) {
+        let sum = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        out.push(sum as u32);
+        carry = sum >> 32;
+    }
+    out.push(carry as u32);
+    trim(&mut out);
+    out
+}
+
+// requires a >= b
+fn sub_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut out = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, limb) in a.iter().enumerate() {
+        let mut diff = *limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if diff < 0 {
+            diff += 1 << 32;
+            borrow = 1;
+        }
+        out.push(diff as u32);
+    }
+    trim(&mut out);
+    out
+}
+
+fn mul_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() {
+        return vec![];
+    }
+    let mut out = vec![0u32; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, y) in b.iter().enumerate() {
+            let t = out[i + j] as u64 + *x as u64 * *y as u64 + carry;
+            out[i + j] = t as u32;
+            carry = t >> 32;
+        }
+        out[i + b.len()] = carry as u32;
+    }
+    trim(&mut out);
+    out
+}
+
+fn mul_small_add(mag: &mut Vec<u32>, m: u32, add: u32) {
+    let mut carry = add as u64;
+    for limb in mag.iter_mut() {
+        let t = *limb as u64 * m as u64 + carry;
+        *limb = t as u32;
+        carry = t >> 32;
+    }
+    if carry != 0 {
+        mag.push(carry as u32);
+    }
+}
+
+fn div_rem_small(a: &[u32], d: u32) -> (Vec<u32>, u32) {
+    let mut q = vec![0u32; a.len()];
+    let mut rem = 0u64;
+    for i in (0..a.len()).rev() {
+        let cur = (rem << 32) | a[i] as u64;
+        q[i] = (cur / d as u64) as u32;
+        rem = cur % d as u64;
+    }
+    trim(&mut q);
+    (q, rem as u32)
+}
+
+// binary long division; b must be non-zero
+fn div_rem_mag(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if b.len() == 1 {
+        let (q, r) = div_rem_small(a, b[0]);
+        let mut r = vec![r];
+        trim(&mut r);
+        return (q, r);
+    }
+    let mut q = vec![0u32; a.len()];
+    let mut r: Vec<u32> = vec![];
+    for i in (0..a.len() * 32).rev() {
+        shl1(&mut r);
+        if a[i / 32] >> (i % 32) & 1 == 1 {
+            match r.first_mut() {
+                Some(low) => *low |= 1,
+                None => r.push(1),
+            }
+        }
+        if cmp_mag(&r, b) != Ordering::Less {
+            r = sub_mag(&r, b);
+            q[i / 32] |= 1 << (i % 32);
+        }
+    }
+    trim(&mut q);
+    (q, r)
+}
+
+fn shl1(mag: &mut Vec<u32>) {
+    let mut carry = 0u32;
+    for limb in mag.iter_mut() {
+        let next = *limb >> 31;
+        *limb = (*limb << 1) | carry;
+        carry = next;
+    }
+    if carry != 0 {
+        mag.push(carry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::objects::bigint::BigInt;
+
+    fn big(s: &str) -> BigInt {
+        BigInt::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        for s in ["0", "1", "-1", "4294967296", "-18446744073709551616", "123456789012345678901234567890"] {
+            assert_eq!(big(s).to_string(), s);
+        }
+        assert_eq!(big("-0").to_string(), "0");
+        assert_eq!(big("000042").to_string(), "42");
+        assert_eq!(BigInt::parse("12a"), None);
+        assert_eq!(BigInt::parse("-"), None);
+    }
+
+    #[test]
+    fn test_matches_i128() {
+        let values: [i128; 9] = [0, 1, -1, 7, -13, 4294967295, -4294967296, 9223372036854775807, -9223372036854775808];
+        for a in values {
+            for b in values {
+                let (x, y) = (big(&a.to_string()), big(&b.to_string()));
+                assert_eq!((&x + &y).to_string(), (a + b).to_string());
+                assert_eq!((&x - &y).to_string(), (a - b).to_string());
+                assert_eq!((&x * &y).to_string(), (a * b).to_string());
+                assert_eq!(x.cmp(&y), a.cmp(&b));
+                match x.div_rem(&y) {
+                    Some((q, r)) => {
+                        assert_eq!(q.to_string(), (a / b).to_string());
+                        assert_eq!(r.to_string(), (a % b).to_string());
+                    }
+                    None => assert_eq!(b, 0),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_large_division() {
+        let a = big("340282366920938463463374607431768211457");
+        let b = big("18446744073709551617");
+        let (q, r) = a.div_rem(&b).unwrap();
+        assert_eq!(q.to_string(), "18446744073709551615");
+        assert_eq!(r.to_string(), "2");
+        assert_eq!((&(&q * &b) + &r), a);
+    }
+
+    #[test]
+    fn test_to_i64() {
+        assert_eq!(big("9223372036854775807").to_i64(), Some(i64::MAX));
+        assert_eq!(big("-9223372036854775808").to_i64(), Some(i64::MIN));
+        assert_eq!(big("9223372036854775808").to_i64(), None);
+        assert_eq!(BigInt::from(i64::MIN).to_string(), "-9223372036854775808");
+        assert_eq!(big("-18446744073709551616").to_f64(), -18446744073709551616.0);
+    }
+}
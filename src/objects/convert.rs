@@ -0,0 +1,243 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::objects::objects::{HashKey, Object};
+
+/// Why an object couldn't be turned into the Rust type asked for. For a
+/// collection it describes the first element that didn't fit.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ConversionError {
+    pub expected: &'static str,
+    pub found: &'static str,
+}
+
+impl ConversionError {
+    fn new(expected: &'static str, found: &Object) -> Self {
+        ConversionError { expected, found: found.type_name() }
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {}, got {}", self.expected, self.found)
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+/// Rust values that have a Monkey counterpart.
+pub trait IntoObject {
+    fn into_object(self) -> Object;
+}
+
+/// Rust values that can be read back out of a Monkey object.
+pub trait FromObject: Sized {
+    fn from_object(obj: &Object) -> Result<Self, ConversionError>;
+}
+
+impl IntoObject for Object {
+    fn into_object(self) -> Object {
+        self
+    }
+}
+
+impl FromObject for Object {
+    fn from_object(obj: &Object) -> Result<Self, ConversionError> {
+        Ok(obj.clone())
+    }
+}
+
+impl IntoObject for () {
+    fn into_object(self) -> Object {
+        Object::Null
+    }
+}
+
+impl IntoObject for i64 {
+    fn into_object(self) -> Object {
+        Object::Int(self)
+    }
+}
+
+impl FromObject for i64 {
+    fn from_object(obj: &Object) -> Result<Self, ConversionError> {
+        match obj {
+            Object::Int(v) => Ok(*v),
+            other => Err(ConversionError::new("64-bit int", other)),
+        }
+    }
+}
+
+impl IntoObject for f64 {
+    fn into_object(self) -> Object {
+        Object::Float(self)
+    }
+}
+
+/// Ints are accepted too, the same way arithmetic promotes them.
+impl FromObject for f64 {
+    fn from_object(obj: &Object) -> Result<Self, ConversionError> {
+        obj.to_f64().ok_or_else(|| ConversionError::new("number", obj))
+    }
+}
+
+impl IntoObject for bool {
+    fn into_object(self) -> Object {
+        Object::Bool(self)
+    }
+}
+
+impl FromObject for bool {
+    fn from_object(obj: &Object) -> Result<Self, ConversionError> {
+        match obj {
+            Object::Bool(v) => Ok(*v),
+            other => Err(ConversionError::new("bool", other)),
+        }
+    }
+}
+
+impl IntoObject for String {
+    fn into_object(self) -> Object {
+        Object::Str(self)
+    }
+}
+
+impl IntoObject for &str {
+    fn into_object(self) -> Object {
+        Object::Str(self.to_string())
+    }
+}
+
+impl FromObject for String {
+    fn from_object(obj: &Object) -> Result<Self, ConversionError> {
+        match obj {
+            Object::Str(v) => Ok(v.clone()),
+            other => Err(ConversionError::new("string", other)),
+        }
+    }
+}
+
+impl<T: IntoObject> IntoObject for Vec<T> {
+    fn into_object(self) -> Object {
+        Object::Array(self.into_iter().map(IntoObject::into_object).collect())
+    }
+}
+
+impl<T: FromObject> FromObject for Vec<T> {
+    fn from_object(obj: &Object) -> Result<Self, ConversionError> {
+        match obj {
+            Object::Array(items) => items.iter().map(T::from_object).collect(),
+            other => Err(ConversionError::new("array", other)),
+        }
+    }
+}
+
+impl<T: IntoObject> IntoObject for HashMap<String, T> {
+    fn into_object(self) -> Object {
+        let pairs = self.into_iter().map(|(k, v)| (HashKey::Str(k), v.into_object()));
+        Object::Hash(pairs.collect::<BTreeMap<_, _>>())
+    }
+}
+
+/// Only hashes whose keys are all strings convert.
+impl<T: FromObject> FromObject for HashMap<String, T> {
+    fn from_object(obj: &Object) -> Result<Self, ConversionError> {
+        let pairs = match obj {
+            Object::Hash(pairs) => pairs,
+            other => return Err(ConversionError::new("hash", other)),
+        };
+        pairs
+            .iter()
+            .map(|(key, value)| match key {
+                HashKey::Str(k) => Ok((k.clone(), T::from_object(value)?)),
+                other => Err(ConversionError::new("string", &other.to_object())),
+            })
+            .collect()
+    }
+}
+
+/// `None` is `null`.
+impl<T: IntoObject> IntoObject for Option<T> {
+    fn into_object(self) -> Object {
+        match self {
+            Some(v) => v.into_object(),
+            None => Object::Null,
+        }
+    }
+}
+
+impl<T: FromObject> FromObject for Option<T> {
+    fn from_object(obj: &Object) -> Result<Self, ConversionError> {
+        match obj {
+            Object::Null => Ok(None),
+            other => T::from_object(other).map(Some),
+        }
+    }
+}
+
+// tuples are arrays of exactly their length
+macro_rules! impl_tuple {
+    ($len:literal; $($name:ident $idx:tt),+) => {
+        impl<$($name: IntoObject),+> IntoObject for ($($name,)+) {
+            fn into_object(self) -> Object {
+                Object::Array(vec![$(self.$idx.into_object()),+])
+            }
+        }
+
+        impl<$($name: FromObject),+> FromObject for ($($name,)+) {
+            fn from_object(obj: &Object) -> Result<Self, ConversionError> {
+                match obj {
+                    Object::Array(items) if items.len() == $len => Ok(($($name::from_object(&items[$idx])?,)+)),
+                    other => Err(ConversionError::new(concat!("array of length ", $len), other)),
+                }
+            }
+        }
+    };
+}
+
+impl_tuple!(1; A 0);
+impl_tuple!(2; A 0, B 1);
+impl_tuple!(3; A 0, B 1, C 2);
+impl_tuple!(4; A 0, B 1, C 2, D 3);
+
+#[cfg(test)]
+mod tests {
+    use crate::objects::convert::{ConversionError, FromObject, IntoObject};
+    use crate::objects::objects::Object;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_into_object() {
+        assert_eq!(5i64.into_object().to_string(), "5");
+        assert_eq!(1.5f64.into_object().to_string(), "1.5");
+        assert_eq!(true.into_object().to_string(), "true");
+        assert_eq!("a".into_object().to_string(), "a");
+        assert_eq!(().into_object().to_string(), "null");
+        assert_eq!(vec![Some(1i64), None].into_object().to_string(), "[1, null]");
+        assert_eq!((1i64, "a".to_string(), vec![true]).into_object().to_string(), "[1, \"a\", [true]]");
+        let map = HashMap::from([("b".to_string(), 2i64), ("a".to_string(), 1i64)]);
+        assert_eq!(map.into_object().to_string(), "{\"a\": 1, \"b\": 2}");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let value = vec![(1i64, Some("x".to_string())), (2, None)];
+        assert_eq!(Vec::<(i64, Option<String>)>::from_object(&value.clone().into_object()), Ok(value));
+        let map = HashMap::from([("xs".to_string(), vec![1i64, 2])]);
+        assert_eq!(HashMap::<String, Vec<i64>>::from_object(&map.clone().into_object()), Ok(map));
+        assert_eq!(f64::from_object(&Object::Int(2)), Ok(2.0));
+    }
+
+    #[test]
+    fn test_from_object_errors() {
+        let mismatch = |expected, found| ConversionError { expected, found };
+        assert_eq!(i64::from_object(&Object::Str("1".to_string())).unwrap_err(), mismatch("64-bit int", "string"));
+        let mixed = vec![Object::Int(1), Object::Bool(true)].into_object();
+        assert_eq!(Vec::<i64>::from_object(&mixed).unwrap_err(), mismatch("64-bit int", "bool"));
+        assert_eq!(<(i64, i64)>::from_object(&vec![1i64].into_object()).unwrap_err(), mismatch("array of length 2", "array"));
+        let hash = Object::Hash([(Object::Int(1).hash_key().unwrap(), Object::Int(1))].into());
+        assert_eq!(HashMap::<String, i64>::from_object(&hash).unwrap_err(), mismatch("string", "int"));
+        assert_eq!(Option::<bool>::from_object(&Object::Null), Ok(None));
+        assert_eq!(Option::<bool>::from_object(&Object::Int(0)).unwrap_err(), mismatch("bool", "int"));
+    }
+}
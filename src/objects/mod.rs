@@ -1 +1,3 @@
+pub mod bigint;
+pub mod convert;
 pub mod objects;
@@ -1,20 +1,228 @@
 use core::fmt;
+use std::collections::BTreeMap;
+use std::rc::Rc;
 
+use crate::ast::ast;
+use crate::eval::environment::Env;
+use crate::eval::error::RuntimeError;
+use crate::objects::bigint::BigInt;
 
+#[derive(Debug, Clone)]
 pub enum Object {
-    Int(i32),
+    Int(i64),
+    /// An integer outside the `i64` range. Arithmetic switches to it on
+    /// overflow, so it never holds a value that would fit in an `Int`.
+    BigInt(BigInt),
+    Float(f64),
+    Str(String),
+    Array(Vec<Object>),
+    /// Kept sorted by key, which also fixes the order it prints in.
+    Hash(BTreeMap<HashKey, Object>),
     Bool(bool),
-    Null
+    Null,
+    /// A function value; `env` is the scope it was defined in, which
+    /// its body sees when called.
+    Function {
+        params: Vec<String>,
+        body: Rc<Vec<ast::Statement>>,
+        env: Env
+    },
+    Builtin(NativeFn)
+}
+
+/// What a native function gives back to the script calling it.
+pub type NativeResult = Result<Object, RuntimeError>;
+
+type NativeBody = dyn Fn(&[Object]) -> NativeResult;
+
+/// A function implemented in Rust rather than in Monkey.
+#[derive(Clone)]
+pub struct NativeFn {
+    pub name: String,
+    func: Rc<NativeBody>,
+}
+
+impl NativeFn {
+    pub fn new(name: &str, func: impl Fn(&[Object]) -> NativeResult + 'static) -> Self {
+        NativeFn { name: name.to_string(), func: Rc::new(func) }
+    }
+
+    pub fn call(&self, args: &[Object]) -> NativeResult {
+        (self.func)(args)
+    }
+}
+
+impl fmt::Debug for NativeFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NativeFn").field("name", &self.name).finish()
+    }
+}
+
+/// The key of a hash entry. Only ints, bools and strings can be keys.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HashKey {
+    Int(i64),
+    BigInt(BigInt),
+    Bool(bool),
+    Str(String),
+}
+
+impl HashKey {
+    pub fn to_object(&self) -> Object {
+        match self {
+            HashKey::Int(v) => Object::Int(*v),
+            HashKey::BigInt(v) => Object::BigInt(v.clone()),
+            HashKey::Bool(v) => Object::Bool(*v),
+            HashKey::Str(v) => Object::Str(v.clone()),
+        }
+    }
+}
+
+impl Object {
+    /// The key this object indexes a hash under, `None` if it's unhashable.
+    pub fn hash_key(&self) -> Option<HashKey> {
+        match self {
+            Object::Int(v) => Some(HashKey::Int(*v)),
+            Object::BigInt(v) => Some(HashKey::BigInt(v.clone())),
+            Object::Bool(v) => Some(HashKey::Bool(*v)),
+            Object::Str(v) => Some(HashKey::Str(v.clone())),
+            _ => None
+        }
+    }
+
+    /// An integer result, narrowed to `Int` when it fits in an `i64`.
+    pub fn from_bigint(v:BigInt) -> Object {
+        match v.to_i64() {
+            Some(n) => Object::Int(n),
+            None => Object::BigInt(v)
+        }
+    }
+
+    /// The value of an `Int` or `BigInt` as a `BigInt`.
+    pub fn to_bigint(&self) -> Option<BigInt> {
+        match self {
+            Object::Int(v) => Some(BigInt::from(*v)),
+            Object::BigInt(v) => Some(v.clone()),
+            _ => None
+        }
+    }
+
+    /// Whether the object counts as true in a condition: `false` and
+    /// `null` are falsy, every other value (including `0`) is truthy.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Object::Bool(false) | Object::Null)
+    }
+
+    /// The value of any number as an `f64`, which is how ints take part
+    /// in arithmetic with floats.
+    pub fn to_f64(&self) -> Option<f64> {
+        match self {
+            Object::Int(v) => Some(*v as f64),
+            Object::BigInt(v) => Some(v.to_f64()),
+            Object::Float(v) => Some(*v),
+            _ => None
+        }
+    }
+
+    /// Value equality between ints, floats, strings, bools and null, and
+    /// between arrays and hashes element by element. Values of different
+    /// types, an int and a float included, are never equal. Functions and
+    /// builtins have no equality and give `None`, as do arrays and hashes
+    /// that get as far as comparing them.
+    pub fn equals(&self, other:&Object) -> Option<bool> {
+        match (self, other) {
+            (Object::Int(a), Object::Int(b)) => Some(a == b),
+            (Object::BigInt(a), Object::BigInt(b)) => Some(a == b),
+            (Object::Float(a), Object::Float(b)) => Some(a == b),
+            (Object::Str(a), Object::Str(b)) => Some(a == b),
+            (Object::Array(a), Object::Array(b)) => {
+                if a.len() != b.len(){
+                    return Some(false)
+                }
+                let mut eq = true;
+                for (x, y) in a.iter().zip(b){
+                    eq &= x.equals(y)?;
+                }
+                Some(eq)
+            },
+            (Object::Hash(a), Object::Hash(b)) => {
+                if a.len() != b.len(){
+                    return Some(false)
+                }
+                let mut eq = true;
+                for (key, x) in a{
+                    match b.get(key){
+                        Some(y) => eq &= x.equals(y)?,
+                        None => return Some(false)
+                    }
+                }
+                Some(eq)
+            },
+            (Object::Bool(a), Object::Bool(b)) => Some(a == b),
+            (Object::Null, Object::Null) => Some(true),
+            (Object::Function { .. }, Object::Function { .. })
+            | (Object::Builtin(_), Object::Builtin(_)) => None,
+            _ => Some(false)
+        }
+    }
+
+    /// The name of the object's type as shown in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self{
+            Object::Int(_) | Object::BigInt(_) => "int",
+            Object::Float(_) => "float",
+            Object::Str(_) => "string",
+            Object::Array(_) => "array",
+            Object::Hash(_) => "hash",
+            Object::Bool(_) => "bool",
+            Object::Null => "null",
+            Object::Function { .. } | Object::Builtin(_) => "function"
+        }
+    }
 }
 
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self{
             Object::Int(v) => write!(f, "{v}"),
+            Object::BigInt(v) => write!(f, "{v}"),
+            // always shows a `.` or an exponent, so floats never look like ints
+            Object::Float(v) => write!(f, "{v:?}"),
+            Object::Str(v) => write!(f, "{v}"),
+            Object::Array(v) => {
+                write!(f, "[")?;
+                for (i, item) in v.iter().enumerate(){
+                    if i > 0{
+                        write!(f, ", ")?;
+                    }
+                    fmt_nested(item, f)?;
+                }
+                write!(f, "]")
+            },
+            Object::Hash(v) => {
+                write!(f, "{{")?;
+                for (i, (key, item)) in v.iter().enumerate(){
+                    if i > 0{
+                        write!(f, ", ")?;
+                    }
+                    fmt_nested(&key.to_object(), f)?;
+                    write!(f, ": ")?;
+                    fmt_nested(item, f)?;
+                }
+                write!(f, "}}")
+            },
             Object::Bool(v) => write!(f, "{v}"),
-            Object::Null => write!(f, "null")
+            Object::Null => write!(f, "null"),
+            Object::Function { params, .. } => write!(f, "fn({})", params.join(", ")),
+            Object::Builtin(native) => write!(f, "builtin {}", native.name)
         }
     }
 }
 
-
+// strings inside collections are quoted so `["a, b"]` can't pass for `["a", "b"]`
+fn fmt_nested(obj:&Object, f:&mut fmt::Formatter<'_>) -> fmt::Result {
+    match obj{
+        Object::Str(s) => write!(f, "{s:?}"),
+        other => write!(f, "{other}")
+    }
+}
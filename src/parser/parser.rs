@@ -1,9 +1,51 @@
-use crate::{ast::ast::{self, Expression, Statement}, lexer::lexer, token::token};
+use crate::{ast::ast::{self, Expression, Statement}, lexer::lexer, token::token::{self, Span}};
+use crate::objects::bigint::BigInt;
+use std::fmt;
+use std::rc::Rc;
 
 pub struct Parser{
     cur_tok: token::Token,
     peek_tok: token::Token,
+    cur_span: Span,
+    peek_span: Span,
     lexer : Box<lexer::Lexer>,
+    errors: Vec<ParseError>,
+    /// `{`s up to and including `cur_tok` that haven't been closed. A stray
+    /// `}` at the top level doesn't count.
+    braces: usize,
+    /// How deeply the expression being parsed is nested.
+    depth: usize,
+}
+
+/// How deeply expressions may nest. Parsing and evaluating an expression
+/// recurse once per level, which takes up to about 8 KB of stack in a debug
+/// build, so this keeps them inside 1 MB. A chain of operators like
+/// `1 + 2 + 3` nests one level per operator.
+pub const MAX_NESTING: usize = 128;
+
+/// What kind of syntax error was found.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParseErrorKind {
+    /// The parser was looking for `expected` and found another token.
+    Unexpected { expected: String, found: token::Token },
+    /// Expressions nest more than `MAX_NESTING` deep.
+    TooDeep,
+}
+
+/// A syntax error and the span of the token it was found at.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub span: Span,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ParseErrorKind::Unexpected { expected, found } => write!(f, "expected {expected}, found {}", found.describe()),
+            ParseErrorKind::TooDeep => write!(f, "expression nested too deeply"),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, PartialOrd)]
@@ -13,134 +55,221 @@ enum Precedence{
     LessGreater,
     Sum,
     Product,
+    Prefix,
+    Call,
+    Index,
 }
 
 
 impl Parser {
-    pub fn new(mut lexer:Box<lexer::Lexer>) -> Self{
-        let cur_tok = lexer.next_token();
-        let peek_tok = lexer.next_token();
-        Parser{lexer:Box::new(*lexer), cur_tok, peek_tok}
+    pub fn new(lexer:Box<lexer::Lexer>) -> Self{
+        let mut lexer = lexer;
+        let cur = lexer.next_token();
+        let peek = lexer.next_token();
+        let mut parser = Parser{lexer, cur_tok: cur.tok, peek_tok: peek.tok, cur_span: cur.span, peek_span: peek.span, errors: vec![], braces: 0, depth: 0};
+        parser.count_braces();
+        parser
     }
 
  
     fn parse_ident(&mut self) -> ast::Expression {
-        ast::Expression::Ident(self.cur_tok.to_string())
+        ast::Expression::Ident(self.cur_tok.to_string(), self.cur_span)
     }
 
-    fn parse_expression_statement(&mut self) -> Option<ast::Statement>{
+    fn parse_expression_statement(&mut self) -> Result<ast::Statement, ParseError>{
         let tok = self.cur_tok.clone();
-        let exprs = self.parse_expression(Precedence::Lowest);
+        let start = self.cur_span;
+        let exprs = self.parse_expression(Precedence::Lowest)?;
         if self.peek_tok == token::Token::Semicolon{
             self.next_token();
         }
-        Some(ast::Statement::ExprsStatement { token: tok,  exprs })
+        Ok(ast::Statement::ExprsStatement { token: tok,  exprs, span: start.to(self.cur_span) })
     }
 
-    fn parse_grouped_expression(&mut self) -> ast::Expression {
+    fn parse_grouped_expression(&mut self) -> Result<ast::Expression, ParseError> {
         self.next_token();
-        let node = self.parse_expression(Precedence::Lowest);
-        if self.peek_tok == token::Token::Rparen{
-            self.next_token();
-        }
-        node
+        let node = self.parse_expression(Precedence::Lowest)?;
+        self.expect_peek(token::Token::Rparen, "`)`")?;
+        Ok(node)
+    }
+
+    fn parse_expression(&mut self, prec:Precedence) -> Result<ast::Expression, ParseError>{
+        let depth = self.depth;
+        let result = self.parse_nested_expression(prec);
+        self.depth = depth;
+        result
     }
 
-    fn parse_expression(&mut self, prec:Precedence) -> ast::Expression{
+    /// Does the work of `parse_expression`, which puts `depth` back
+    /// afterwards. It goes up by one for the expression and by one more for
+    /// each operator, call or index applied to it.
+    fn parse_nested_expression(&mut self, prec:Precedence) -> Result<ast::Expression, ParseError>{
+        self.nest()?;
         let mut left = match &self.cur_tok{
             token::Token::Int(_) => self.parse_int(),
-            token::Token::True => self.parse_bool(),
+            token::Token::Float(_) => self.parse_float(),
+            token::Token::Str(s) => Ok(ast::Expression::Str(s.clone(), self.cur_span)),
+            token::Token::StrHead(_) => self.parse_interpolation(),
+            token::Token::True => Ok(self.parse_bool()),
             token::Token::Func => self.parse_func(),
-            token::Token::False => self.parse_bool(),
+            token::Token::False => Ok(self.parse_bool()),
             token::Token::Minus => self.parse_prefix_ops(),
             token::Token::Bang => self.parse_prefix_ops(),
             token::Token::Lparen => self.parse_grouped_expression(),
             token::Token::If => self.parse_if(),
-            token::Token::Ident(_) => self.parse_ident(),
-            _ => ast::Expression::NoExprsn
-        };
+            token::Token::Lbracket => self.parse_array(),
+            // blocks only follow `if`, `else` and `fn`, so a brace that
+            // starts an expression opens a hash literal
+            token::Token::Lbrace => self.parse_hash(),
+            token::Token::Ident(_) => Ok(self.parse_ident()),
+            _ => Err(self.error_at_cur("an expression"))
+        }?;
 
         while self.cur_tok != token::Token::Semicolon && self.token_to_precedence(self.peek_tok.clone()) > prec{
-            match &self.peek_tok {
+            self.nest()?;
+            left = match &self.peek_tok {
                 token::Token::Plus|
                     token::Token::Minus|
                     token::Token::Asterisk|
                     token::Token::Slash|
+                    token::Token::Percent|
                     token::Token::Eq|
                     token::Token::Gt|
                     token::Token::Lt|
                     token::Token::NotEq => {
                         self.next_token();
-                        left = self.parse_infix(left)
+                        self.parse_infix(left)
                     }
+                token::Token::Lparen => {
+                    self.next_token();
+                    self.parse_call(left)
+                }
+                token::Token::Lbracket => {
+                    self.next_token();
+                    self.parse_index(left)
+                }
 
-                _ => return Expression::NoExprsn
-            }
+                _ => break
+            }?;
         }
 
-        left
+        Ok(left)
     } 
 
-    fn parse_params(&mut self) -> Vec<ast::Expression>{
+    fn nest(&mut self) -> Result<(), ParseError> {
+        if self.depth >= MAX_NESTING{
+            return Err(ParseError { kind: ParseErrorKind::TooDeep, span: self.cur_span })
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn parse_params(&mut self) -> Result<Vec<ast::Expression>, ParseError>{
         let mut params: Vec<Expression> = vec![];
-        self.next_token();
+        self.expect_peek(token::Token::Lparen, "`(`")?;
 
         if self.peek_tok == token::Token::Rparen{
-            return params
+            self.next_token();
+            return Ok(params)
         }
 
 
-        self.next_token();
+        self.expect_ident()?;
 
 
-        let ident = ast::Expression::Ident(self.cur_tok.to_string());
+        let ident = self.parse_ident();
 
         params.push(ident);
 
 
         while self.peek_tok == token::Token::Comma{
             self.next_token();
-            self.next_token();
+            self.expect_ident()?;
             let ident = self.parse_ident();
             params.push(ident);
         }
 
-        params
+        self.expect_peek(token::Token::Rparen, "`,` or `)`")?;
+        Ok(params)
 
     }
 
-    fn parse_func(&mut self) -> ast::Expression {
-        let params = self.parse_params();
-        self.next_token();
-        self.next_token();
-        let body = self.parse_block();
-        ast::Expression::FnExprsn { params, body }
+    fn parse_func(&mut self) -> Result<ast::Expression, ParseError> {
+        let start = self.cur_span;
+        let params = self.parse_params()?;
+        self.expect_peek(token::Token::Lbrace, "`{`")?;
+        let body = self.parse_block()?;
+        Ok(ast::Expression::FnExprsn { params, body: Rc::new(body), span: start.to(self.cur_span) })
     }
 
     fn parse_bool(&self) -> ast::Expression {
-        ast::Expression::Boolean{ token:self.cur_tok.clone(), value: self.cur_tok == token::Token::True}
+        ast::Expression::Boolean{ token:self.cur_tok.clone(), value: self.cur_tok == token::Token::True, span: self.cur_span}
     }
 
-    fn parse_int(&mut self) -> ast::Expression {
+    fn parse_int(&mut self) -> Result<ast::Expression, ParseError> {
         let literal = match &self.cur_tok{
             token::Token::Int(s) => s,
-            _ => return ast::Expression::NoExprsn 
+            _ => return Err(self.error_at_cur("an integer")) 
         };
-        let int: i32 = match literal.parse(){
-            Ok(n) => n,
-            Err(_s) => return ast::Expression::NoExprsn
+        if let Ok(n) = literal.parse::<i64>(){
+            return Ok(ast::Expression::Int(n, self.cur_span))
+        }
+        match BigInt::parse(literal){
+            Some(n) => Ok(ast::Expression::BigInt(n, self.cur_span)),
+            None => Err(self.error_at_cur("an integer"))
+        }
+    }
+
+
+    fn parse_float(&mut self) -> Result<ast::Expression, ParseError> {
+        let value = match &self.cur_tok{
+            token::Token::Float(s) => s.parse::<f64>().ok(),
+            _ => None
         };
-        ast::Expression::Int(int)    
+        match value{
+            Some(v) => Ok(ast::Expression::Float(v, self.cur_span)),
+            None => Err(self.error_at_cur("a float"))
+        }
+    }
+
+
+    fn parse_interpolation(&mut self) -> Result<ast::Expression, ParseError> {
+        let start = self.cur_span;
+        let mut parts = vec![];
+        let mut text = match &self.cur_tok{
+            token::Token::StrHead(s) => s.clone(),
+            _ => return Err(self.error_at_cur("a string"))
+        };
+        let mut text_span = self.cur_span;
+        loop{
+            if !text.is_empty(){
+                parts.push(ast::Expression::Str(text, text_span));
+            }
+            self.next_token();
+            parts.push(self.parse_expression(Precedence::Lowest)?);
+            self.next_token();
+            text_span = self.cur_span;
+            match &self.cur_tok{
+                token::Token::StrMid(s) => text = s.clone(),
+                token::Token::StrTail(s) => {
+                    if !s.is_empty(){
+                        parts.push(ast::Expression::Str(s.clone(), text_span));
+                    }
+                    break
+                },
+                _ => return Err(self.error_at_cur("`}` to close the interpolation"))
+            }
+        }
+        Ok(ast::Expression::Interpolated { parts, span: start.to(self.cur_span) })
     }
 
 
-    fn parse_statemnt(&mut self) -> Option<ast::Statement> {
+    fn parse_statemnt(&mut self) -> Result<ast::Statement, ParseError> {
         match self.cur_tok {
            token::Token::Let => {
                self.parse_let() 
            },
            token::Token::Return => {
-               println!("return detected");
                self.parse_return()
            },
            _ => self.parse_expression_statement() 
@@ -151,150 +280,291 @@ impl Parser {
     fn token_to_precedence(&self,tok:token::Token) -> Precedence{
         match tok {
             token::Token::Slash =>  Precedence::Product,
+            token::Token::Percent =>  Precedence::Product,
             token::Token::Gt =>  Precedence::LessGreater,
             token::Token::Lt =>  Precedence::LessGreater,
             token::Token::Asterisk =>  Precedence::Product,
             token::Token::Eq =>  Precedence::Equals,
-            token::Token::NotEq =>  Precedence::Product,
+            token::Token::NotEq =>  Precedence::Equals,
             token::Token::Plus =>  Precedence::Sum,
             token::Token::Minus =>  Precedence::Sum,
+            token::Token::Lparen =>  Precedence::Call,
+            token::Token::Lbracket =>  Precedence::Index,
             _ => Precedence::Lowest
         }
     }
 
 
-    fn parse_return(&mut self) -> Option<ast::Statement> {
+    fn parse_return(&mut self) -> Result<ast::Statement, ParseError> {
         let return_tok = self.cur_tok.clone();     
+        let start = self.cur_span;
         self.next_token();
-        let return_value = self.parse_expression(Precedence::Lowest);
-        if self.peek_tok != token::Token::Semicolon{
-            return None;
-        }
+        let return_value = self.parse_expression(Precedence::Lowest)?;
         // move to the semi-colon
-        self.next_token();
-        Some(ast::Statement::Return{ token: return_tok, exprs: return_value })
+        self.expect_peek(token::Token::Semicolon, "`;`")?;
+        Ok(ast::Statement::Return{ token: return_tok, exprs: return_value, span: start.to(self.cur_span) })
     }
 
-    fn parse_let(&mut self) -> Option<ast::Statement>{
+    fn parse_let(&mut self) -> Result<ast::Statement, ParseError>{
         let let_tok = self.cur_tok.clone();
-        match &self.peek_tok{
-            token::Token::Ident(_) => self.next_token(),
-            _ => return None,
-        }
-        let name = self.parse_ident().to_string();
-        if self.peek_tok != token::Token::Assing{
-            return None
-        }
-        self.next_token();
+        let start = self.cur_span;
+        self.expect_ident()?;
+        let name = self.parse_ident();
+        self.expect_peek(token::Token::Assing, "`=`")?;
         self.next_token();
-        let val = self.parse_expression(Precedence::Lowest);
+        let val = self.parse_expression(Precedence::Lowest)?;
         // move to the Semicolon
-        self.next_token();
-        Some(ast::Statement::Let { token: let_tok, ident: ast::Expression::Ident(name), exprs: val})
+        self.expect_peek(token::Token::Semicolon, "`;`")?;
+        Ok(ast::Statement::Let { token: let_tok, ident: name, exprs: val, span: start.to(self.cur_span)})
     }
 
     fn next_token(&mut self) {
-        self.cur_tok = self.peek_tok.clone();
-        self.peek_tok = self.lexer.next_token()
+        let peek = self.lexer.next_token();
+        self.cur_tok = std::mem::replace(&mut self.peek_tok, peek.tok);
+        self.cur_span = std::mem::replace(&mut self.peek_span, peek.span);
+        self.count_braces();
     }
 
-    pub fn parse_program(&mut self) -> Vec<ast::Statement>{
+    fn count_braces(&mut self) {
+        match self.cur_tok {
+            token::Token::Lbrace => self.braces += 1,
+            token::Token::Rbrace => self.braces = self.braces.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    /// Moves onto the peek token if it is `tok`, otherwise reports it as
+    /// unexpected.
+    fn expect_peek(&mut self, tok:token::Token, expected:&str) -> Result<(), ParseError> {
+        if self.peek_tok != tok{
+            return Err(self.error_at_peek(expected))
+        }
+        self.next_token();
+        Ok(())
+    }
+
+    fn expect_ident(&mut self) -> Result<(), ParseError> {
+        match self.peek_tok{
+            token::Token::Ident(_) => {
+                self.next_token();
+                Ok(())
+            },
+            _ => Err(self.error_at_peek("an identifier"))
+        }
+    }
+
+    fn error_at_cur(&self, expected:&str) -> ParseError {
+        ParseError { kind: ParseErrorKind::Unexpected { expected: expected.to_string(), found: self.cur_tok.clone() }, span: self.cur_span }
+    }
+
+    fn error_at_peek(&self, expected:&str) -> ParseError {
+        ParseError { kind: ParseErrorKind::Unexpected { expected: expected.to_string(), found: self.peek_tok.clone() }, span: self.peek_span }
+    }
+
+    /// Skips the rest of a broken statement, stopping on a `;` or just
+    /// before a `let` or `return`, so that the caller's `next_token` lands
+    /// on a fresh statement. Braces the statement opens are skipped whole.
+    /// `block` is the brace depth inside the block being parsed, if any: the
+    /// parser stops just before the `}` closing it, or on that `}` if the
+    /// broken statement already took it, and says which. At the top level a
+    /// `}` is stray and gets skipped too.
+    fn synchronize(&mut self, block: Option<usize>) -> bool {
+        let level = block.unwrap_or(0);
+        while self.cur_tok != token::Token::Eof{
+            if block.is_some() && self.braces < level{
+                return true
+            }
+            if self.braces == level{
+                match (&self.cur_tok, &self.peek_tok) {
+                    (token::Token::Semicolon, _) => return false,
+                    (_, token::Token::Let | token::Token::Return | token::Token::Eof) => return false,
+                    (_, token::Token::Rbrace) if block.is_some() => return false,
+                    _ => {}
+                }
+            }
+            self.next_token();
+        }
+        false
+    }
+
+    /// Parses the whole input. Syntax errors don't stop the parse: the
+    /// parser resyncs at the next statement and every error is reported.
+    pub fn parse_program(&mut self) -> Result<ast::Program, Vec<ParseError>>{
         let mut statements = vec![];
         while  self.cur_tok != token::Token::Eof{
-            let stmnt = self.parse_statemnt();
-            match stmnt {
-                Some(n) =>  statements.push(n),
-                None => return statements
+            match self.parse_statemnt() {
+                Ok(n) =>  statements.push(n),
+                Err(e) => {
+                    self.errors.push(e);
+                    self.synchronize(None);
+                }
             }
             self.next_token();
         } 
 
-        statements
+        if !self.errors.is_empty(){
+            return Err(std::mem::take(&mut self.errors))
+        }
+        Ok(statements)
     }
 
-    fn parse_prefix_ops(&mut self) -> ast::Expression{
+    fn parse_prefix_ops(&mut self) -> Result<ast::Expression, ParseError>{
         let tok = self.cur_tok.clone(); 
+        let start = self.cur_span;
         self.next_token();
-        let right = self.parse_expression(Precedence::Lowest);
-        ast::Expression::PrefixExprsn { token: tok, exprsn: Box::new(right) }
+        let right = self.parse_expression(Precedence::Prefix)?;
+        Ok(ast::Expression::PrefixExprsn { token: tok, exprsn: Box::new(right), span: start.to(self.cur_span) })
     }
 
-    fn parse_infix(&mut self, left:ast::Expression) -> ast::Expression {
+    fn parse_infix(&mut self, left:ast::Expression) -> Result<ast::Expression, ParseError> {
         let opr = self.cur_tok.clone();
         self.next_token();
-        let right  = self.parse_expression(self.token_to_precedence(opr.clone()));
-        ast::Expression::InfixExprsn { left: Box::new(left), right: Box::new(right), oprt: opr.to_string() }
+        let right  = self.parse_expression(self.token_to_precedence(opr.clone()))?;
+        let span = left.span().to(self.cur_span);
+        Ok(ast::Expression::InfixExprsn { left: Box::new(left), right: Box::new(right), oprt: opr.to_string(), span })
     }
     
-    fn parse_block(&mut self) -> Vec<ast::Statement>{
-        self.next_token();
-        let mut stmnts :Vec<Statement> = vec![];    
-        while self.cur_tok != token::Token::Rbrace && self.cur_tok != token::Token::Eof{
-            match self.parse_statemnt(){
-                Some(s) => stmnts.push(s),
-                None => return stmnts
-            };
+    fn parse_call(&mut self, function:ast::Expression) -> Result<ast::Expression, ParseError> {
+        let args = self.parse_expression_list(token::Token::Rparen, "`,` or `)`")?;
+        let span = function.span().to(self.cur_span);
+        Ok(ast::Expression::Call { function: Box::new(function), args, span })
+    }
+
+    fn parse_array(&mut self) -> Result<ast::Expression, ParseError> {
+        let start = self.cur_span;
+        let elements = self.parse_expression_list(token::Token::Rbracket, "`,` or `]`")?;
+        Ok(ast::Expression::Array { elements, span: start.to(self.cur_span) })
+    }
+
+    fn parse_hash(&mut self) -> Result<ast::Expression, ParseError> {
+        let start = self.cur_span;
+        let mut pairs = vec![];
+        while self.peek_tok != token::Token::Rbrace{
+            self.next_token();
+            let key = self.parse_expression(Precedence::Lowest)?;
+            self.expect_peek(token::Token::Colon, "`:`")?;
             self.next_token();
+            let value = self.parse_expression(Precedence::Lowest)?;
+            pairs.push((key, value));
+            if self.peek_tok != token::Token::Rbrace{
+                self.expect_peek(token::Token::Comma, "`,` or `}`")?;
+            }
         }
-        stmnts
+        self.next_token();
+        Ok(ast::Expression::Hash { pairs, span: start.to(self.cur_span) })
     }
 
-    fn parse_if(&mut self) -> Expression {
+    fn parse_index(&mut self, left:ast::Expression) -> Result<ast::Expression, ParseError> {
         self.next_token();
-        let condt = self.parse_expression(Precedence::Lowest);
-        if self.peek_tok != token::Token::Lbrace{
-            return ast::Expression::NoExprsn
+        let index = self.parse_expression(Precedence::Lowest)?;
+        self.expect_peek(token::Token::Rbracket, "`]`")?;
+        let span = left.span().to(self.cur_span);
+        Ok(ast::Expression::Index { left: Box::new(left), index: Box::new(index), span })
+    }
+
+    /// Parses comma separated expressions up to `end`, starting on the
+    /// token before the first one and finishing on `end`.
+    fn parse_expression_list(&mut self, end:token::Token, expected:&str) -> Result<Vec<ast::Expression>, ParseError> {
+        let mut list = vec![];
+        if self.peek_tok == end{
+            self.next_token();
+            return Ok(list)
         }
         self.next_token();
-        let consq = self.parse_block();
+        list.push(self.parse_expression(Precedence::Lowest)?);
+        while self.peek_tok == token::Token::Comma{
+            self.next_token();
+            self.next_token();
+            list.push(self.parse_expression(Precedence::Lowest)?);
+        }
+        self.expect_peek(end, expected)?;
+        Ok(list)
+    }
+
+    fn parse_block(&mut self) -> Result<Vec<ast::Statement>, ParseError>{
+        let level = self.braces;
+        self.next_token();
+        let mut stmnts :Vec<Statement> = vec![];    
+        while self.cur_tok != token::Token::Rbrace{
+            if self.cur_tok == token::Token::Eof{
+                return Err(self.error_at_cur("`}`"))
+            }
+            match self.parse_statemnt() {
+                Ok(s) => stmnts.push(s),
+                Err(e) => {
+                    self.errors.push(e);
+                    // the broken statement ran into the end of the block
+                    if self.synchronize(Some(level)){
+                        break
+                    }
+                }
+            }
+            self.next_token();
+        }
+        Ok(stmnts)
+    }
+
+    fn parse_if(&mut self) -> Result<Expression, ParseError> {
+        let start = self.cur_span;
         self.next_token();
-        if self.cur_tok == token::Token::Else{
+        let condt = self.parse_expression(Precedence::Lowest)?;
+        self.expect_peek(token::Token::Lbrace, "`{`")?;
+        let consq = self.parse_block()?;
+        if self.peek_tok == token::Token::Else{
             self.next_token();
-            let altr = self.parse_block();
-            let node = ast::Expression::IfExprsn { condt: Box::new(condt), conseq: consq, alter: altr };
-            return node
+            self.expect_peek(token::Token::Lbrace, "`{`")?;
+            let altr = self.parse_block()?;
+            let node = ast::Expression::IfExprsn { condt: Box::new(condt), conseq: consq, alter: altr, span: start.to(self.cur_span) };
+            return Ok(node)
         }
-        ast::Expression::IfExprsn { condt: Box::new(condt) , conseq: consq, alter: vec![] }
+        Ok(ast::Expression::IfExprsn { condt: Box::new(condt) , conseq: consq, alter: vec![], span: start.to(self.cur_span) })
     }
 }
 
 
 #[cfg(test)]
 mod tests {
-    use crate::token::token;
+    use crate::token::token::{self, Span};
     use crate::{ast, lexer, parser};
     use crate::lexer::Lexer; 
     use crate::parser::Parser;
+    use crate::parser::parser::{ParseErrorKind, MAX_NESTING};
+    use std::rc::Rc;
+
+    fn shape(stmnts:&[ast::ast::Statement]) -> Vec<ast::ast::Statement> {
+        stmnts.iter().map(|s| s.without_spans()).collect()
+    }
+
     #[test]
     fn test_let(){
         let src = "let x = 2;".to_string();
         let lex = Lexer::new(src);
         let mut parser  = Parser::new(Box::new(lex));
-        let statements = parser.parse_program();
+        let statements = parser.parse_program().unwrap();
         if statements.len() != 1{
             panic!("expected only one statement got {}", statements.len())
         }
-        let node = ast::ast::Statement::Let { 
+        let node = ast::ast::Statement::Let { span: Span::default(), 
             token: crate::token::Token::Let, 
-            ident: ast::ast::Expression::Ident("x".to_string()),
-            exprs: ast::ast::Expression::Int(2)
+            ident: ast::ast::Expression::Ident("x".to_string(), Span::default()),
+            exprs: ast::ast::Expression::Int(2, Span::default())
         };
-        assert_eq!(statements[0], node)
+        assert_eq!(statements[0].without_spans(), node)
     }
     #[test]
     fn test_return(){
         let src = "return 12;".to_string();
         let lex = Lexer::new(src);
         let mut parser  = Parser::new(Box::new(lex));
-        let statements = parser.parse_program();
+        let statements = parser.parse_program().unwrap();
         if statements.len() != 1{
             panic!("expected only one statement got {}", statements.len())
         }
-        let node = ast::ast::Statement::Return { 
+        let node = ast::ast::Statement::Return { span: Span::default(), 
             token: crate::token::Token::Return, 
-            exprs: ast::ast::Expression::Int(12)
+            exprs: ast::ast::Expression::Int(12, Span::default())
         };
-        assert_eq!(statements[0], node)
+        assert_eq!(statements[0].without_spans(), node)
     }
 
     #[test]
@@ -302,15 +572,15 @@ mod tests {
         let src = "12;".to_string();
         let lex = lexer::Lexer::new(src);
         let mut p = parser::Parser::new(Box::new(lex));
-        let stmnts = p.parse_program();
-        let expected = ast::ast::Statement::ExprsStatement { 
+        let stmnts = p.parse_program().unwrap();
+        let expected = ast::ast::Statement::ExprsStatement { span: Span::default(), 
             token: token::Token::Int("12".to_string()), 
-            exprs: ast::ast::Expression::Int(12) 
+            exprs: ast::ast::Expression::Int(12, Span::default()) 
         };
         if stmnts.len() != 1{
             panic!("expected 1 statement got {}", stmnts.len())
         }
-        assert_eq!(stmnts[0], expected)
+        assert_eq!(stmnts[0].without_spans(), expected)
     }
 
     #[test]
@@ -318,21 +588,21 @@ mod tests {
         let src = "false; true;".to_string();
         let lex = lexer::Lexer::new(src);
         let mut p = parser::Parser::new(Box::new(lex));
-        let stmnts = p.parse_program();
+        let stmnts = p.parse_program().unwrap();
         let expected = [ 
-            ast::ast::Statement::ExprsStatement { 
+            ast::ast::Statement::ExprsStatement { span: Span::default(), 
                 token: token::Token::False, 
-                exprs: ast::ast::Expression::Boolean { token: token::Token::False, value: false } 
+                exprs: ast::ast::Expression::Boolean { span: Span::default(), token: token::Token::False, value: false } 
             },
-            ast::ast::Statement::ExprsStatement { 
+            ast::ast::Statement::ExprsStatement { span: Span::default(), 
                 token: token::Token::True, 
-                exprs: ast::ast::Expression::Boolean { token: token::Token::True, value: true } 
+                exprs: ast::ast::Expression::Boolean { span: Span::default(), token: token::Token::True, value: true } 
             },
         ];
         if stmnts.len() != 2{
             panic!("expected 1 statement got {}", stmnts.len())
         }
-        assert_eq!(stmnts, expected)
+        assert_eq!(shape(&stmnts), expected)
     }
 
     #[test]
@@ -340,50 +610,50 @@ mod tests {
         let src = "!true;".to_string();
         let lex = lexer::Lexer::new(src);
         let mut p = parser::Parser::new(Box::new(lex));
-        let stmnts = p.parse_program();
+        let stmnts = p.parse_program().unwrap();
         if stmnts.len() != 1{
             panic!("expected 1 got {}", stmnts.len())
         }
         let expected = [
-            ast::ast::Statement::ExprsStatement { token: token::Token::Bang, exprs: 
-                ast::ast::Expression::PrefixExprsn { 
+            ast::ast::Statement::ExprsStatement { span: Span::default(), token: token::Token::Bang, exprs: 
+                ast::ast::Expression::PrefixExprsn { span: Span::default(), 
                     token: token::Token::Bang,
-                    exprsn: Box::new(ast::ast::Expression::Boolean { 
+                    exprsn: Box::new(ast::ast::Expression::Boolean { span: Span::default(), 
                         token: token::Token::True, 
                         value: true 
                     })
                 }
         }
         ];
-        assert_eq!(stmnts[0], expected[0])
+        assert_eq!(stmnts[0].without_spans(), expected[0])
     }
     #[test]
     fn test_infix(){
         let src = "1+1; 1+2*3;".to_string();
         let lex = lexer::Lexer::new(src);
         let mut p = parser::Parser::new(Box::new(lex));
-        let stmnts = p.parse_program();
+        let stmnts = p.parse_program().unwrap();
         if stmnts.len() != 2{
             panic!("expected 2 got {}", stmnts.len())
         }
         let expected = [
-            ast::ast::Statement::ExprsStatement { token: token::Token::Int("1".to_string()), 
-            exprs: ast::ast::Expression::InfixExprsn { 
-                left: Box::new(ast::ast::Expression::Int(1)), 
-                right: Box::new(ast::ast::Expression::Int(1)), 
+            ast::ast::Statement::ExprsStatement { span: Span::default(), token: token::Token::Int("1".to_string()), 
+            exprs: ast::ast::Expression::InfixExprsn { span: Span::default(), 
+                left: Box::new(ast::ast::Expression::Int(1, Span::default())), 
+                right: Box::new(ast::ast::Expression::Int(1, Span::default())), 
                 oprt: "+".to_string() 
             }
         },
-        ast::ast::Statement::ExprsStatement { token: token::Token::Int("1".to_string()), 
-            exprs: ast::ast::Expression::InfixExprsn { 
-                left: Box::new(ast::ast::Expression::Int(1)), 
-                right: Box::new(ast::ast::Expression::InfixExprsn { left: Box::new(ast::ast::Expression::Int(2)), right: Box::new(ast::ast::Expression::Int(3)), oprt: "*".to_string() }), 
+        ast::ast::Statement::ExprsStatement { span: Span::default(), token: token::Token::Int("1".to_string()), 
+            exprs: ast::ast::Expression::InfixExprsn { span: Span::default(), 
+                left: Box::new(ast::ast::Expression::Int(1, Span::default())), 
+                right: Box::new(ast::ast::Expression::InfixExprsn { span: Span::default(), left: Box::new(ast::ast::Expression::Int(2, Span::default())), right: Box::new(ast::ast::Expression::Int(3, Span::default())), oprt: "*".to_string() }), 
                 oprt: "+".to_string() 
             }
         },
         ];
         for (i, v) in stmnts.iter().enumerate(){
-           assert_eq!(*v, expected[i]) 
+           assert_eq!(v.without_spans(), expected[i]) 
         }
     }
 
@@ -392,7 +662,7 @@ mod tests {
         let src = "(1 + 1) * 2".to_string();
         let lex = lexer::Lexer::new(src);
         let mut p = parser::Parser::new(Box::new(lex));
-        let stmnts = p.parse_program();
+        let stmnts = p.parse_program().unwrap();
 
 
         if stmnts.len() != 1{
@@ -400,20 +670,20 @@ mod tests {
         }
 
 
-        let expected = ast::ast::Statement::ExprsStatement { 
+        let expected = ast::ast::Statement::ExprsStatement { span: Span::default(), 
             token: token::Token::Lparen, 
-            exprs: ast::ast::Expression::InfixExprsn { 
-                right: Box::new(ast::ast::Expression::Int(2)), 
+            exprs: ast::ast::Expression::InfixExprsn { span: Span::default(), 
+                right: Box::new(ast::ast::Expression::Int(2, Span::default())), 
                 left: Box::new(
-                    ast::ast::Expression::InfixExprsn { 
-                        left: Box::new(ast::ast::Expression::Int(1)), 
-                        right: Box::new(ast::ast::Expression::Int(1)), 
+                    ast::ast::Expression::InfixExprsn { span: Span::default(), 
+                        left: Box::new(ast::ast::Expression::Int(1, Span::default())), 
+                        right: Box::new(ast::ast::Expression::Int(1, Span::default())), 
                         oprt: "+".to_string() 
                 }), 
                 oprt: "*".to_string() 
             }
         };
-        assert_eq!(stmnts[0], expected)
+        assert_eq!(stmnts[0].without_spans(), expected)
     }
 
 
@@ -429,34 +699,34 @@ mod tests {
         ".to_string();
         let lex = lexer::Lexer::new(src);
         let mut p = parser::Parser::new(Box::new(lex));
-        let stmnts = p.parse_program();
+        let stmnts = p.parse_program().unwrap();
 
         if stmnts.len() != 1{
             panic!("expected 1 got {}", stmnts.len())
         }
         let consq = vec![
-            ast::ast::Statement::Let { token: token::Token::Let, ident: ast::ast::Expression::Ident("x".to_string()), exprs: ast::ast::Expression::Int(2) },
-            ast::ast::Statement::Return { token: token::Token::Return, exprs: ast::ast::Expression::Int(12)},
+            ast::ast::Statement::Let { span: Span::default(), token: token::Token::Let, ident: ast::ast::Expression::Ident("x".to_string(), Span::default()), exprs: ast::ast::Expression::Int(2, Span::default()) },
+            ast::ast::Statement::Return { span: Span::default(), token: token::Token::Return, exprs: ast::ast::Expression::Int(12, Span::default())},
 
         ];
         let smnts = vec![
-            ast::ast::Statement::Let { token: token::Token::Let, ident: ast::ast::Expression::Ident("b".to_string()), exprs: ast::ast::Expression::Int(2) },
-            ast::ast::Statement::Return { token: token::Token::Return, exprs: ast::ast::Expression::Int(3) },
+            ast::ast::Statement::Let { span: Span::default(), token: token::Token::Let, ident: ast::ast::Expression::Ident("b".to_string(), Span::default()), exprs: ast::ast::Expression::Int(2, Span::default()) },
+            ast::ast::Statement::Return { span: Span::default(), token: token::Token::Return, exprs: ast::ast::Expression::Int(3, Span::default()) },
         ]; 
 
-        let expected = ast::ast::Statement::ExprsStatement { 
+        let expected = ast::ast::Statement::ExprsStatement { span: Span::default(), 
             token: token::Token::If, 
-            exprs: ast::ast::Expression::IfExprsn { 
-                condt: Box::new(ast::ast::Expression::InfixExprsn { 
-                    left: Box::new(ast::ast::Expression::Ident("x".to_string())), 
-                    right: Box::new(ast::ast::Expression::Int(1)), 
+            exprs: ast::ast::Expression::IfExprsn { span: Span::default(), 
+                condt: Box::new(ast::ast::Expression::InfixExprsn { span: Span::default(), 
+                    left: Box::new(ast::ast::Expression::Ident("x".to_string(), Span::default())), 
+                    right: Box::new(ast::ast::Expression::Int(1, Span::default())), 
                     oprt: ">".to_string() 
                 }), 
                 conseq: consq, 
                 alter: smnts
             } 
         };
-        assert_eq!(stmnts[0], expected)
+        assert_eq!(stmnts[0].without_spans(), expected)
     }
 
 
@@ -471,25 +741,328 @@ mod tests {
         ".to_string();
         let lex = lexer::Lexer::new(src);
         let mut p = parser::Parser::new(Box::new(lex));
-        let stmnts = p.parse_program();
+        let stmnts = p.parse_program().unwrap();
 
         if stmnts.len() != 1{
             panic!("expected 1 got {}", stmnts.len())
         }
-        let expected = ast::ast::Statement::ExprsStatement { 
+        let expected = ast::ast::Statement::ExprsStatement { span: Span::default(), 
             token: token::Token::Func, 
-            exprs:  ast::ast::Expression::FnExprsn { 
+            exprs:  ast::ast::Expression::FnExprsn { span: Span::default(), 
                 params: vec![
-                    ast::ast::Expression::Ident("param".to_string()),
-                    ast::ast::Expression::Ident("paramsecond".to_string())
+                    ast::ast::Expression::Ident("param".to_string(), Span::default()),
+                    ast::ast::Expression::Ident("paramsecond".to_string(), Span::default())
                 ], 
-                body: vec![
-                    ast::ast::Statement::Let { token: token::Token::Let, exprs: ast::ast::Expression::Int(12), ident: ast::ast::Expression::Ident("x".to_string()) },
-                    ast::ast::Statement::Return { token: token::Token::Return, exprs: ast::ast::Expression::Int(12)}, 
-                ] 
+                body: Rc::new(vec![
+                    ast::ast::Statement::Let { span: Span::default(), token: token::Token::Let, exprs: ast::ast::Expression::Int(12, Span::default()), ident: ast::ast::Expression::Ident("x".to_string(), Span::default()) },
+                    ast::ast::Statement::Return { span: Span::default(), token: token::Token::Return, exprs: ast::ast::Expression::Int(12, Span::default())}, 
+                ]) 
             }
         };
-        assert_eq!(stmnts[0], expected)
+        assert_eq!(stmnts[0].without_spans(), expected)
+    }
+
+    #[test]
+    fn test_spans(){
+        let src = "let x = 1;\n  -y + 20;".to_string();
+        let lex = lexer::Lexer::new(src);
+        let mut p = parser::Parser::new(Box::new(lex));
+        let stmnts = p.parse_program().unwrap();
+        let exprs = match &stmnts[1]{
+            ast::ast::Statement::ExprsStatement { exprs, .. } => exprs,
+            other => panic!("expected an expression statement got {}", other)
+        };
+        let span = exprs.span();
+        assert_eq!((span.start, span.end, span.line, span.col), (13, 20, 2, 3));
+
+        // the same code in two places only differs by its spans
+        let mut p = parser::Parser::new(Box::new(lexer::Lexer::new("x + 1;\nx + 1;".to_string())));
+        let stmnts = p.parse_program().unwrap();
+        assert_ne!(stmnts[0], stmnts[1]);
+        assert_eq!(stmnts[0].without_spans(), stmnts[1].without_spans());
+    }
+
+    #[test]
+    fn test_errors(){
+        let cases = [
+            ("let = 5;", "an identifier", token::Token::Assing, (1, 5)),
+            ("(1 + 2;", "`)`", token::Token::Semicolon, (1, 7)),
+            ("let x = 1\nreturn x;", "`;`", token::Token::Return, (2, 1)),
+            ("if x { 1; ", "`}`", token::Token::Eof, (1, 11)),
+            ("1 + ;", "an expression", token::Token::Semicolon, (1, 5)),
+        ];
+        for (src, expected, found, (line, col)) in cases{
+            let lex = lexer::Lexer::new(src.to_string());
+            let mut p = parser::Parser::new(Box::new(lex));
+            let errors = p.parse_program().unwrap_err();
+            if errors.len() != 1{
+                panic!("expected 1 error for {:?} got {}", src, errors.len())
+            }
+            assert_eq!(errors[0].kind, ParseErrorKind::Unexpected { expected: expected.to_string(), found });
+            assert_eq!((errors[0].span.line, errors[0].span.col), (line, col));
+        }
+
+        let messages = [
+            ("let x = 1\nlet y = 2;", "expected `;`, found `let`"),
+            ("let x 5;", "expected `=`, found integer `5`"),
+            ("if x { 1 } else 2", "expected `{`, found integer `2`"),
+            ("let 1.5 = 1;", "expected an identifier, found float `1.5`"),
+            ("f(1 \"a\")", "expected `,` or `)`, found string \"a\""),
+            ("let x = é;", "expected an expression, found invalid input `é`"),
+            ("(1", "expected `)`, found end of input"),
+            ("let true = 1;", "expected an identifier, found `true`"),
+        ];
+        for (src, message) in messages{
+            let mut p = parser::Parser::new(Box::new(lexer::Lexer::new(src.to_string())));
+            assert_eq!(p.parse_program().unwrap_err()[0].to_string(), message, "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_error_recovery(){
+        let src = "
+            let = 1;
+            let y 2;
+            let ok = fn(x) {
+                let = x;
+                x + ;
+                return x;
+            };
+            (1 + 2;
+            let z = 3;
+        ".to_string();
+        let lex = lexer::Lexer::new(src);
+        let mut p = parser::Parser::new(Box::new(lex));
+        let errors = p.parse_program().unwrap_err();
+        let lines: Vec<usize> = errors.iter().map(|e| e.span.line).collect();
+        assert_eq!(lines, vec![2, 3, 5, 6, 9]);
+    }
+
+    #[test]
+    fn test_nesting_limit(){
+        let parse = |src:String| Parser::new(Box::new(Lexer::new(src))).parse_program();
+        let n = MAX_NESTING / 2;
+        assert!(parse(format!("{}1{};", "(".repeat(n), ")".repeat(n))).is_ok());
+        assert!(parse(format!("{}1;", "-".repeat(n))).is_ok());
+        assert!(parse(vec!["1"; n].join(" + ")).is_ok());
+
+        let too_deep = [
+            format!("{}1{};\nlet x = 1;", "(".repeat(2000), ")".repeat(2000)),
+            format!("puts({}1);", "-".repeat(200_000)),
+            format!("{}1{}", "[".repeat(MAX_NESTING), "]".repeat(MAX_NESTING)),
+            format!("{}1{}", "if (x) { ".repeat(MAX_NESTING), " }".repeat(MAX_NESTING)),
+            vec!["1"; MAX_NESTING * 2].join(" + "),
+        ];
+        for src in too_deep{
+            let errors = parse(src.clone()).unwrap_err();
+            assert_eq!(errors.len(), 1, "{:.40}: {:?}", src, errors);
+            assert_eq!(errors[0].kind, ParseErrorKind::TooDeep);
+            assert_eq!(errors[0].to_string(), "expression nested too deeply");
+        }
+    }
+
+    #[test]
+    fn test_one_typo_one_error(){
+        let cases = [
+            ("let x = if (a { 1 } else { 2 };\nlet y = 1;", "expected `)`, found `{`"),
+            ("let f = fn(x,) {};\nf(1);", "expected an identifier, found `)`"),
+            ("fn(x,) { x }", "expected an identifier, found `)`"),
+            ("let h = {\"a\" 1, \"b\": {\"c\": 2}};\nh;", "expected `:`, found integer `1`"),
+            ("let f = fn(x) { if x { 1 } else { 2 } + };\nf(1);", "expected an expression, found `}`"),
+            ("let x = 1 + }\nlet y = 2;", "expected an expression, found `}`"),
+            ("let f = fn() { let = 1; if a { 2 } };\nf();", "expected an identifier, found `=`"),
+            ("if (a) { (if (b) { 1 } }\nlet z = 2;", "expected `)`, found `}`"),
+        ];
+        for (src, message) in cases{
+            let mut p = parser::Parser::new(Box::new(lexer::Lexer::new(src.to_string())));
+            let errors = p.parse_program().unwrap_err();
+            let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            assert_eq!(messages, vec![message.to_string()], "{}", src);
+        }
     }
-}
 
+    #[test]
+    fn test_call(){
+        let src = "add(1, 2 * 3)(x); f();".to_string();
+        let lex = lexer::Lexer::new(src);
+        let mut p = parser::Parser::new(Box::new(lex));
+        let stmnts = p.parse_program().unwrap();
+        let inner = ast::ast::Expression::Call { span: Span::default(),
+            function: Box::new(ast::ast::Expression::Ident("add".to_string(), Span::default())),
+            args: vec![
+                ast::ast::Expression::Int(1, Span::default()),
+                ast::ast::Expression::InfixExprsn { span: Span::default(),
+                    left: Box::new(ast::ast::Expression::Int(2, Span::default())),
+                    right: Box::new(ast::ast::Expression::Int(3, Span::default())),
+                    oprt: "*".to_string()
+                },
+            ]
+        };
+        let expected = [
+            ast::ast::Statement::ExprsStatement { span: Span::default(), token: token::Token::Ident("add".to_string()),
+                exprs: ast::ast::Expression::Call { span: Span::default(),
+                    function: Box::new(inner),
+                    args: vec![ast::ast::Expression::Ident("x".to_string(), Span::default())]
+                }
+            },
+            ast::ast::Statement::ExprsStatement { span: Span::default(), token: token::Token::Ident("f".to_string()),
+                exprs: ast::ast::Expression::Call { span: Span::default(),
+                    function: Box::new(ast::ast::Expression::Ident("f".to_string(), Span::default())),
+                    args: vec![]
+                }
+            },
+        ];
+        assert_eq!(shape(&stmnts), expected)
+    }
+
+    #[test]
+    fn test_prefix_precedence(){
+        let src = "-1 + 2; !f(x);".to_string();
+        let lex = lexer::Lexer::new(src);
+        let mut p = parser::Parser::new(Box::new(lex));
+        let stmnts = p.parse_program().unwrap();
+        let expected = [
+            ast::ast::Statement::ExprsStatement { span: Span::default(), token: token::Token::Minus,
+                exprs: ast::ast::Expression::InfixExprsn { span: Span::default(),
+                    left: Box::new(ast::ast::Expression::PrefixExprsn { span: Span::default(),
+                        token: token::Token::Minus,
+                        exprsn: Box::new(ast::ast::Expression::Int(1, Span::default()))
+                    }),
+                    right: Box::new(ast::ast::Expression::Int(2, Span::default())),
+                    oprt: "+".to_string()
+                }
+            },
+            ast::ast::Statement::ExprsStatement { span: Span::default(), token: token::Token::Bang,
+                exprs: ast::ast::Expression::PrefixExprsn { span: Span::default(),
+                    token: token::Token::Bang,
+                    exprsn: Box::new(ast::ast::Expression::Call { span: Span::default(),
+                        function: Box::new(ast::ast::Expression::Ident("f".to_string(), Span::default())),
+                        args: vec![ast::ast::Expression::Ident("x".to_string(), Span::default())]
+                    })
+                }
+            },
+        ];
+        assert_eq!(shape(&stmnts), expected)
+    }
+
+    #[test]
+    fn test_interpolation(){
+        let src = "\"hi ${name}!\"; \"${a}${b}\"".to_string();
+        let lex = lexer::Lexer::new(src);
+        let mut p = parser::Parser::new(Box::new(lex));
+        let stmnts = p.parse_program().unwrap();
+        let expected = [
+            ast::ast::Statement::ExprsStatement { span: Span::default(), token: token::Token::StrHead("hi ".to_string()),
+                exprs: ast::ast::Expression::Interpolated { span: Span::default(),
+                    parts: vec![
+                        ast::ast::Expression::Str("hi ".to_string(), Span::default()),
+                        ast::ast::Expression::Ident("name".to_string(), Span::default()),
+                        ast::ast::Expression::Str("!".to_string(), Span::default()),
+                    ]
+                }
+            },
+            ast::ast::Statement::ExprsStatement { span: Span::default(), token: token::Token::StrHead(String::new()),
+                exprs: ast::ast::Expression::Interpolated { span: Span::default(),
+                    parts: vec![
+                        ast::ast::Expression::Ident("a".to_string(), Span::default()),
+                        ast::ast::Expression::Ident("b".to_string(), Span::default()),
+                    ]
+                }
+            },
+        ];
+        assert_eq!(shape(&stmnts), expected);
+
+        let lex = lexer::Lexer::new("\"${1 2}\";".to_string());
+        let mut p = parser::Parser::new(Box::new(lex));
+        let errors = p.parse_program().unwrap_err();
+        let found = token::Token::Int("2".to_string());
+        assert_eq!(errors[0].kind, ParseErrorKind::Unexpected { expected: "`}` to close the interpolation".to_string(), found });
+    }
+
+    #[test]
+    fn test_array_and_index(){
+        let src = "[1, 2 * 2]; a * b[1]; f(x)[0];".to_string();
+        let lex = lexer::Lexer::new(src);
+        let mut p = parser::Parser::new(Box::new(lex));
+        let stmnts = p.parse_program().unwrap();
+        let expected = [
+            ast::ast::Statement::ExprsStatement { span: Span::default(), token: token::Token::Lbracket,
+                exprs: ast::ast::Expression::Array { span: Span::default(),
+                    elements: vec![
+                        ast::ast::Expression::Int(1, Span::default()),
+                        ast::ast::Expression::InfixExprsn { span: Span::default(),
+                            left: Box::new(ast::ast::Expression::Int(2, Span::default())),
+                            right: Box::new(ast::ast::Expression::Int(2, Span::default())),
+                            oprt: "*".to_string()
+                        },
+                    ]
+                }
+            },
+            ast::ast::Statement::ExprsStatement { span: Span::default(), token: token::Token::Ident("a".to_string()),
+                exprs: ast::ast::Expression::InfixExprsn { span: Span::default(),
+                    left: Box::new(ast::ast::Expression::Ident("a".to_string(), Span::default())),
+                    right: Box::new(ast::ast::Expression::Index { span: Span::default(),
+                        left: Box::new(ast::ast::Expression::Ident("b".to_string(), Span::default())),
+                        index: Box::new(ast::ast::Expression::Int(1, Span::default()))
+                    }),
+                    oprt: "*".to_string()
+                }
+            },
+            ast::ast::Statement::ExprsStatement { span: Span::default(), token: token::Token::Ident("f".to_string()),
+                exprs: ast::ast::Expression::Index { span: Span::default(),
+                    left: Box::new(ast::ast::Expression::Call { span: Span::default(),
+                        function: Box::new(ast::ast::Expression::Ident("f".to_string(), Span::default())),
+                        args: vec![ast::ast::Expression::Ident("x".to_string(), Span::default())]
+                    }),
+                    index: Box::new(ast::ast::Expression::Int(0, Span::default()))
+                }
+            },
+        ];
+        assert_eq!(shape(&stmnts), expected)
+    }
+
+    #[test]
+    fn test_hash(){
+        let src = "{\"a\": 1, 2: {}, true: x + 1}; if c { {1: 2} };".to_string();
+        let lex = lexer::Lexer::new(src);
+        let mut p = parser::Parser::new(Box::new(lex));
+        let stmnts = p.parse_program().unwrap();
+        let expected = [
+            ast::ast::Statement::ExprsStatement { span: Span::default(), token: token::Token::Lbrace,
+                exprs: ast::ast::Expression::Hash { span: Span::default(),
+                    pairs: vec![
+                        (ast::ast::Expression::Str("a".to_string(), Span::default()), ast::ast::Expression::Int(1, Span::default())),
+                        (ast::ast::Expression::Int(2, Span::default()), ast::ast::Expression::Hash { span: Span::default(), pairs: vec![] }),
+                        (
+                            ast::ast::Expression::Boolean { span: Span::default(), token: token::Token::True, value: true },
+                            ast::ast::Expression::InfixExprsn { span: Span::default(),
+                                left: Box::new(ast::ast::Expression::Ident("x".to_string(), Span::default())),
+                                right: Box::new(ast::ast::Expression::Int(1, Span::default())),
+                                oprt: "+".to_string()
+                            }
+                        ),
+                    ]
+                }
+            },
+            ast::ast::Statement::ExprsStatement { span: Span::default(), token: token::Token::If,
+                exprs: ast::ast::Expression::IfExprsn { span: Span::default(),
+                    condt: Box::new(ast::ast::Expression::Ident("c".to_string(), Span::default())),
+                    conseq: vec![
+                        ast::ast::Statement::ExprsStatement { span: Span::default(), token: token::Token::Lbrace,
+                            exprs: ast::ast::Expression::Hash { span: Span::default(),
+                                pairs: vec![(ast::ast::Expression::Int(1, Span::default()), ast::ast::Expression::Int(2, Span::default()))]
+                            }
+                        }
+                    ],
+                    alter: vec![]
+                }
+            },
+        ];
+        assert_eq!(shape(&stmnts), expected);
+
+        let lex = lexer::Lexer::new("{1: 2 3: 4};".to_string());
+        let mut p = parser::Parser::new(Box::new(lex));
+        let errors = p.parse_program().unwrap_err();
+        assert_eq!(errors[0].kind, ParseErrorKind::Unexpected { expected: "`,` or `}`".to_string(), found: token::Token::Int("3".to_string()) });
+    }
+}
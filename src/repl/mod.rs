@@ -0,0 +1,3 @@
+pub mod repl;
+
+pub use repl::{is_incomplete, run};
@@ -0,0 +1,147 @@
+use crate::eval::Output;
+use crate::interpreter::Interpreter;
+use crate::lexer::Lexer;
+use crate::token::Token;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+const PROMPT: &str = ">> ";
+const CONTINUATION_PROMPT: &str = ".. ";
+
+/// Whether `src` stops partway through an expression: inside a string or
+/// with a `(`, `[` or `{` that hasn't been closed yet.
+pub fn is_incomplete(src: &str) -> bool {
+    let mut lexer = Lexer::new(src.to_string());
+    let mut depth: i64 = 0;
+    loop {
+        match lexer.next_token().tok {
+            Token::Lparen | Token::Lbracket | Token::Lbrace | Token::StrHead(_) => depth += 1,
+            Token::Rparen | Token::Rbracket | Token::Rbrace | Token::StrTail(_) => depth -= 1,
+            Token::Unterminated(_) => return true,
+            Token::Eof => return depth > 0,
+            _ => {}
+        }
+    }
+}
+
+/// Reads Monkey from `input` until it runs out, evaluating each complete
+/// entry with `interp` and writing its value, and anything it prints, to
+/// `out`. Errors go to `err` and don't end the session, and bindings carry
+/// over from one entry to the next. An empty line evaluates an incomplete
+/// entry as it stands.
+pub fn run(mut interp: Interpreter, input: impl BufRead, out: Output, err: &mut impl Write, prompt: bool, colour: bool) -> io::Result<()> {
+    interp.set_output(Rc::clone(&out));
+    let mut entry = String::new();
+    let mut lines = input.lines();
+    loop {
+        if prompt {
+            let mut out = out.borrow_mut();
+            write!(out, "{}", if entry.is_empty() { PROMPT } else { CONTINUATION_PROMPT })?;
+            out.flush()?;
+        }
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        if entry.is_empty() && line.trim().is_empty() {
+            continue;
+        }
+        let forced = line.trim().is_empty();
+        entry.push_str(&line);
+        entry.push('\n');
+        if !forced && is_incomplete(&entry) {
+            continue;
+        }
+        eval_entry(&mut interp, &entry, &out, err, colour)?;
+        entry.clear();
+    }
+    if !entry.is_empty() {
+        eval_entry(&mut interp, &entry, &out, err, colour)?;
+    }
+    if prompt {
+        writeln!(out.borrow_mut())?;
+    }
+    Ok(())
+}
+
+fn eval_entry(interp: &mut Interpreter, entry: &str, out: &Output, err: &mut impl Write, colour: bool) -> io::Result<()> {
+    match interp.eval_str(entry) {
+        Ok(result) => writeln!(out.borrow_mut(), "{result}"),
+        Err(e) => write!(err, "{}", e.render("<repl>", entry, colour)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::Interpreter;
+    use crate::repl::{is_incomplete, run};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn run_with(input: &str, prompt: bool) -> (String, String) {
+        let (out, mut err) = (Rc::new(RefCell::new(vec![])), vec![]);
+        run(Interpreter::new(), input.as_bytes(), out.clone(), &mut err, prompt, false).unwrap();
+        let out = out.borrow().clone();
+        (String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    fn session(input: &str) -> (String, String) {
+        run_with(input, false)
+    }
+
+    #[test]
+    fn test_is_incomplete() {
+        let incomplete = [
+            "let f = fn(x) {",
+            "add(1,",
+            "[1, 2",
+            "if (x) { if y { 1 }",
+            "\"open",
+            "\"line\\\"",
+            "\"${1 +",
+            "\"${ {\"a\": 1}[\"a\"] } and ${",
+        ];
+        for src in incomplete {
+            assert!(is_incomplete(src), "{}", src);
+        }
+        let complete = ["", "1 + 2;", "let f = fn(x) { x };", "\"done\"", "\"${1}\"", "\"bad \\q\"", "\"x\\q\\\\\"", "1 + 2)", "}"];
+        for src in complete {
+            assert!(!is_incomplete(src), "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_session() {
+        let (out, err) = session("let x = 5;\nx * 2\n\nlet add = fn(a, b) {\n  a + b\n};\nadd(x,\n 1)\n");
+        assert_eq!(out, "null\n10\nnull\n6\n");
+        assert_eq!(err, "");
+    }
+
+    #[test]
+    fn test_puts_goes_to_out() {
+        let (out, err) = session("puts(1, \"a\")\n");
+        assert_eq!((out.as_str(), err.as_str()), ("1\na\nnull\n", ""));
+    }
+
+    #[test]
+    fn test_errors_keep_the_session_going() {
+        let (out, err) = session("let x = 1;\nx + y\nlet y = 2;\nx + y\n");
+        assert_eq!(out, "null\nnull\n3\n");
+        assert!(err.starts_with("error: unknown identifier `y`\n --> <repl>:1:5\n"), "{}", err);
+
+        // an empty line gives up on an unfinished entry
+        let (out, err) = session("let f = fn() {\n\n1\n");
+        assert_eq!(out, "1\n");
+        assert!(err.starts_with("error: expected `}`, found end of input\n"), "{}", err);
+
+        // a closed string with a bad escape is an error, not an open string
+        let (out, err) = session("\"x\\q\\\\\"\n1\n");
+        assert_eq!(out, "1\n");
+        assert!(err.starts_with("error: expected an expression, found invalid input"), "{}", err);
+    }
+
+    #[test]
+    fn test_prompts() {
+        assert_eq!(run_with("[1,\n2]\n", true).0, ">> .. [1, 2]\n>> \n");
+    }
+}
@@ -1,3 +1,3 @@
 pub mod token;
-pub use token::Token;
+pub use token::{Span, SpannedToken, Token};
 
@@ -1,5 +1,40 @@
 use core::fmt;
 
+/// A region of the source: the byte range `start..end` plus the line and
+/// column (both 1-based) of its first character.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, col: usize) -> Self {
+        Span { start, end, line, col }
+    }
+
+    /// The smallest span covering both `self` and `other`, where `other`
+    /// ends at or after `self`.
+    pub fn to(self, other: Span) -> Span {
+        Span { start: self.start, end: other.end.max(self.end), line: self.line, col: self.col }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+/// A token together with the span of source it was read from.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpannedToken {
+    pub tok: Token,
+    pub span: Span,
+}
+
 #[derive(Hash,Eq,Debug, PartialEq, Clone)]
 pub enum Token {
     Ident(String),
@@ -7,6 +42,12 @@ pub enum Token {
     Bang,
     Let,
     Int(String),
+    Float(String),
+    Str(String),
+    // the pieces of an interpolated string around its `${...}` parts
+    StrHead(String),
+    StrMid(String),
+    StrTail(String),
     Assing,
     Plus,
     Minus,
@@ -14,10 +55,13 @@ pub enum Token {
     True,
     If,
     Slash,
+    Percent,
     Asterisk,
     Else,
     Func,
     Illgl(String),
+    // a string still open at the end of input, with its raw source
+    Unterminated(String),
     Return,
     Eof,
     Eq,
@@ -28,19 +72,49 @@ pub enum Token {
     Lparen,
     Lbrace,
     Rbrace,
-    Comma
+    Lbracket,
+    Rbracket,
+    Comma,
+    Colon
+}
+
+impl Token {
+    /// How the token is named in messages shown to the user, e.g.
+    /// "`else`", "integer `5`" or "end of input".
+    pub fn describe(&self) -> String {
+        match self{
+            Token::Ident(x) => format!("identifier `{x}`"),
+            Token::Int(x) => format!("integer `{x}`"),
+            Token::Float(x) => format!("float `{x}`"),
+            Token::Str(x) => format!("string {x:?}"),
+            Token::StrHead(_) | Token::StrMid(_) | Token::StrTail(_) => "interpolated string".to_string(),
+            Token::Illgl(x) => format!("invalid input `{x}`"),
+            Token::Unterminated(_) => "unterminated string".to_string(),
+            Token::Eof => "end of input".to_string(),
+            Token::Let => "`let`".to_string(),
+            Token::True => "`true`".to_string(),
+            Token::False => "`false`".to_string(),
+            Token::If => "`if`".to_string(),
+            Token::Else => "`else`".to_string(),
+            Token::Func => "`fn`".to_string(),
+            other => format!("`{other}`"),
+        }
+    }
 }
 
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        return match self{
+        match self{
             Token::Return => write!(f, "return"),
             Token::Gt=> write!(f, ">"),
             Token::Comma=> write!(f, ","),
+            Token::Colon=> write!(f, ":"),
             Token::Lparen=> write!(f, "("),
             Token::Lbrace=> write!(f, "{{"),
             Token::Rbrace=> write!(f, "}}"),
             Token::Rparen=> write!(f, ")"),
+            Token::Lbracket=> write!(f, "["),
+            Token::Rbracket=> write!(f, "]"),
             Token::Lt=> write!(f, "<"),
             Token::Eq => write!(f, "=="),
             Token::NotEq => write!(f, "!="),
@@ -50,10 +124,16 @@ impl fmt::Display for Token {
             Token::Minus => write!(f, "-"),
             Token::Asterisk => write!(f, "*"),
             Token::Slash => write!(f, "/"),
+            Token::Percent => write!(f, "%"),
             Token::Let => write!(f, "Let"),
             Token::Ident(x) => write!(f, "{}", x),
             Token::Semicolon => write!(f, ";"),
             Token::Int(x) => write!(f, "Int {}", x),
+            Token::Float(x) => write!(f, "Float {}", x),
+            Token::Str(x) => write!(f, "{:?}", x),
+            Token::StrHead(x) => write!(f, "\"{}${{", x.escape_debug()),
+            Token::StrMid(x) => write!(f, "}}{}${{", x.escape_debug()),
+            Token::StrTail(x) => write!(f, "}}{}\"", x.escape_debug()),
             Token::Assing => write!(f, "="),
             Token::True => write!(f, "TRUE"),
             Token::If =>  write!(f, "If"),
@@ -61,6 +141,7 @@ impl fmt::Display for Token {
             Token::Else =>  write!(f, "Else"),
             Token::Func =>  write!(f, "Func"),
             Token::Illgl(x) => write!(f, "ILLEGAL {}", x),
+            Token::Unterminated(x) => write!(f, "UNTERMINATED {}", x),
         }
     }
 }